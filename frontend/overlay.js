@@ -2,8 +2,10 @@ const { listen } = window.__TAURI__.event;
 
 const overlay = document.getElementById("overlay");
 const textNode = document.getElementById("text");
+const badgeNode = document.getElementById("badge");
 const canvas = document.getElementById("wave");
 const ctx = canvas.getContext("2d");
+const LOW_CONFIDENCE = 0.5;
 
 const state = {
   visible: false,
@@ -11,6 +13,7 @@ const state = {
   targetAmplitude: 0.04,
   text: "",
   phase: 0,
+  mode: "transcribe",
 };
 
 function drawWavePath(sign = 1) {
@@ -85,12 +88,57 @@ listen("overlay-hide", hideOverlay);
 listen("overlay-reset", () => {
   state.text = "";
   textNode.textContent = "";
+  badgeNode.textContent = "";
+  badgeNode.classList.add("hidden");
   state.targetAmplitude = 0.04;
 });
 listen("overlay-text", ({ payload }) => {
-  state.text += payload;
+  const chars = Array.from(state.text);
+  chars.splice(Math.max(0, chars.length - payload.retract));
+  state.text = chars.join("") + payload.insert;
   textNode.textContent = state.text;
 });
+listen("overlay-final", ({ payload }) => {
+  const words = Array.isArray(payload) ? payload : [];
+  if (words.map((word) => word.text).join(" ") !== state.text.trim()) {
+    return;
+  }
+  textNode.textContent = "";
+  words.forEach((word, index) => {
+    if (index > 0) {
+      textNode.append(" ");
+    }
+    const span = document.createElement("span");
+    span.textContent = word.text;
+    if (word.probability < LOW_CONFIDENCE) {
+      span.classList.add("low-confidence");
+    }
+    textNode.append(span);
+  });
+});
+listen("overlay-language", ({ payload }) => {
+  if (state.mode === "command") {
+    return;
+  }
+  if (state.mode === "translate") {
+    badgeNode.textContent = `${payload} → en`;
+  } else {
+    badgeNode.textContent = String(payload);
+  }
+  badgeNode.classList.remove("hidden");
+});
+listen("overlay-mode", ({ payload }) => {
+  state.mode = payload;
+  overlay.classList.toggle("translate", payload === "translate");
+  overlay.classList.toggle("command", payload === "command");
+  if (payload === "translate") {
+    badgeNode.textContent = "→ en";
+    badgeNode.classList.remove("hidden");
+  } else if (payload === "command") {
+    badgeNode.textContent = "command";
+    badgeNode.classList.remove("hidden");
+  }
+});
 listen("overlay-wave", ({ payload }) => {
   const level = Number(payload) || 0;
   state.targetAmplitude = Math.min(0.95, 0.08 + level * 1.35);
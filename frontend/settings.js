@@ -12,10 +12,48 @@ const els = {
   sensitivity: document.getElementById("sensitivity"),
   sensitivityValue: document.getElementById("sensitivity_value"),
   model: document.getElementById("model"),
+  partialModel: document.getElementById("partial_model"),
+  modelList: document.getElementById("model_list"),
+  modelImportPath: document.getElementById("model_import_path"),
+  modelImportSha256: document.getElementById("model_import_sha256"),
+  modelImport: document.getElementById("model_import"),
+  batchInput: document.getElementById("batch_input"),
+  batchFormat: document.getElementById("batch_format"),
+  batchTranscribe: document.getElementById("batch_transcribe"),
+  language: document.getElementById("language"),
+  backend: document.getElementById("backend"),
+  httpEndpoint: document.getElementById("http_endpoint"),
+  httpModel: document.getElementById("http_model"),
   hotkey: document.getElementById("push_to_talk_hotkey"),
+  translateHotkey: document.getElementById("translate_hotkey"),
+  translateByDefault: document.getElementById("translate_by_default"),
+  commandHotkey: document.getElementById("command_hotkey"),
+  commandsPath: document.getElementById("commands_path"),
+  reloadCommands: document.getElementById("reload_commands"),
+  vocabularyList: document.getElementById("vocabulary_list"),
+  vocabularyTerm: document.getElementById("vocabulary_term"),
+  vocabularyAdd: document.getElementById("vocabulary_add"),
+  correctVocabulary: document.getElementById("correct_vocabulary"),
+  snippetList: document.getElementById("snippet_list"),
+  snippetAdd: document.getElementById("snippet_add"),
+  snippetFile: document.getElementById("snippet_file"),
+  snippetImport: document.getElementById("snippet_import"),
+  snippetExport: document.getElementById("snippet_export"),
+  spokenPunctuation: document.getElementById("spoken_punctuation"),
+  voiceCommands: document.getElementById("voice_commands"),
+  blocklist: document.getElementById("hallucination_blocklist"),
+  partialBeamSize: document.getElementById("partial_beam_size"),
+  finalBeamSize: document.getElementById("final_beam_size"),
+  finalTemperatureFallback: document.getElementById("final_temperature_fallback"),
+  decodeThreads: document.getElementById("decode_threads"),
+  modelLoadPolicy: document.getElementById("model_load_policy"),
+  modelIdleMinutes: document.getElementById("model_idle_minutes"),
+  carryContext: document.getElementById("carry_context"),
+  contextWindowSeconds: document.getElementById("context_window_seconds"),
   save: document.getElementById("save"),
   requestPermissions: document.getElementById("request_permissions"),
   status: document.getElementById("status"),
+  healthList: document.getElementById("health_list"),
   permissionsNotice: document.getElementById("permissions_notice"),
 };
 
@@ -23,14 +61,78 @@ function setStatus(message) {
   els.status.textContent = message;
 }
 
+const COMPONENT_LABELS = {
+  microphone: "Microphone",
+  wake_word: "Wake word",
+  transcriber: "Transcription",
+};
+const health = new Map();
+let decodeStats = null;
+
+function renderHealth() {
+  els.healthList.innerHTML = "";
+  for (const [component, label] of Object.entries(COMPONENT_LABELS)) {
+    const entry = health.get(component);
+    const item = document.createElement("li");
+    const text = document.createElement("span");
+    const stateName = entry ? entry.state : "starting";
+    text.textContent = entry?.reason ? `${label} · ${stateName}: ${entry.reason}` : `${label} · ${stateName}`;
+    text.classList.add(`health-${stateName}`);
+    item.append(text);
+    els.healthList.append(item);
+  }
+  if (decodeStats?.real_time_factor != null) {
+    const item = document.createElement("li");
+    const speed = decodeStats.real_time_factor.toFixed(2);
+    item.textContent = `Decoding · ${speed}× real time, previews every ${decodeStats.decode_interval_ms} ms`;
+    els.healthList.append(item);
+  }
+}
+
 function currentFormSettings() {
   return {
+    ...state.settings,
     enabled: els.enabled.checked,
     launch_at_startup: els.launchAtStartup.checked,
     microphone: els.microphone.value,
     sensitivity: Number(els.sensitivity.value),
     model: els.model.value,
+    partial_model: els.partialModel.value || null,
+    language: els.language.value,
     push_to_talk_hotkey: els.hotkey.value.trim() || "Cmd+Shift+Space",
+    translate_hotkey: els.translateHotkey.value.trim(),
+    default_mode: els.translateByDefault.checked ? "translate" : "transcribe",
+    command_hotkey: els.commandHotkey.value.trim(),
+    correct_vocabulary: els.correctVocabulary.checked,
+    spoken_punctuation: els.spokenPunctuation.checked,
+    voice_commands: els.voiceCommands.checked,
+    snippets: readSnippets(),
+    hallucination_blocklist: els.blocklist.value
+      .split("\n")
+      .map((phrase) => phrase.trim())
+      .filter(Boolean),
+    partial_decoding: {
+      ...state.settings.partial_decoding,
+      beam_size: Number(els.partialBeamSize.value) || 1,
+    },
+    final_decoding: {
+      ...state.settings.final_decoding,
+      beam_size: Number(els.finalBeamSize.value) || 1,
+      temperature_increment: els.finalTemperatureFallback.checked
+        ? state.settings.final_decoding.temperature_increment || 0.2
+        : 0,
+    },
+    decode_threads: Math.max(0, Math.floor(Number(els.decodeThreads.value) || 0)),
+    model_load_policy: els.modelLoadPolicy.value,
+    model_idle_minutes: Math.max(1, Math.floor(Number(els.modelIdleMinutes.value) || 10)),
+    carry_context: els.carryContext.checked,
+    context_window_seconds: Math.max(5, Math.floor(Number(els.contextWindowSeconds.value) || 60)),
+    backend: els.backend.value,
+    http_backend: {
+      ...state.settings.http_backend,
+      endpoint: els.httpEndpoint.value.trim() || "http://127.0.0.1:8080/inference",
+      model: els.httpModel.value.trim(),
+    },
   };
 }
 
@@ -41,7 +143,126 @@ function hydrateForm(settings) {
   els.sensitivity.value = settings.sensitivity ?? 0.45;
   els.sensitivityValue.value = Number(els.sensitivity.value).toFixed(2);
   els.model.value = settings.model;
+  els.language.value = settings.language;
   els.hotkey.value = settings.push_to_talk_hotkey;
+  els.translateHotkey.value = settings.translate_hotkey;
+  els.translateByDefault.checked = settings.default_mode === "translate";
+  els.commandHotkey.value = settings.command_hotkey ?? "";
+  els.backend.value = settings.backend;
+  els.httpEndpoint.value = settings.http_backend.endpoint;
+  els.httpModel.value = settings.http_backend.model;
+  els.correctVocabulary.checked = Boolean(settings.correct_vocabulary);
+  els.spokenPunctuation.checked = settings.spoken_punctuation ?? true;
+  els.voiceCommands.checked = settings.voice_commands ?? true;
+  els.blocklist.value = (settings.hallucination_blocklist ?? []).join("\n");
+  els.partialBeamSize.value = settings.partial_decoding.beam_size;
+  els.finalBeamSize.value = settings.final_decoding.beam_size;
+  els.finalTemperatureFallback.checked = settings.final_decoding.temperature_increment > 0;
+  els.decodeThreads.value = settings.decode_threads ?? 0;
+  els.modelLoadPolicy.value = settings.model_load_policy ?? "always";
+  els.modelIdleMinutes.value = settings.model_idle_minutes ?? 10;
+  els.modelIdleMinutes.disabled = els.modelLoadPolicy.value !== "unload_when_idle";
+  els.carryContext.checked = Boolean(settings.carry_context);
+  els.contextWindowSeconds.value = settings.context_window_seconds ?? 60;
+  els.contextWindowSeconds.disabled = !els.carryContext.checked;
+  renderVocabulary(settings.vocabulary ?? []);
+  renderSnippets(settings.snippets ?? []);
+}
+
+function renderVocabulary(terms) {
+  state.settings.vocabulary = terms;
+  els.vocabularyList.innerHTML = "";
+
+  for (const term of terms) {
+    const item = document.createElement("li");
+    item.textContent = term;
+
+    const remove = document.createElement("button");
+    remove.type = "button";
+    remove.textContent = "×";
+    remove.title = `Remove ${term}`;
+    remove.addEventListener("click", async () => {
+      try {
+        renderVocabulary(await invoke("remove_vocabulary_term", { term }));
+      } catch (error) {
+        setStatus(String(error));
+      }
+    });
+
+    item.append(remove);
+    els.vocabularyList.append(item);
+  }
+}
+
+async function addVocabularyTerm() {
+  const term = els.vocabularyTerm.value.trim();
+  if (!term) {
+    return;
+  }
+  renderVocabulary(await invoke("add_vocabulary_term", { term }));
+  els.vocabularyTerm.value = "";
+}
+
+function renderSnippets(snippets) {
+  els.snippetList.innerHTML = "";
+  for (const snippet of snippets) {
+    addSnippetRow(snippet);
+  }
+}
+
+function addSnippetRow(snippet) {
+  const item = document.createElement("li");
+
+  const phrase = document.createElement("input");
+  phrase.type = "text";
+  phrase.className = "snippet-phrase";
+  phrase.placeholder = "insert my signature";
+  phrase.value = snippet.phrase;
+
+  const text = document.createElement("textarea");
+  text.className = "snippet-text";
+  text.rows = 2;
+  text.placeholder = "Best regards,\nSam — {date}";
+  text.value = snippet.text;
+
+  const remove = document.createElement("button");
+  remove.type = "button";
+  remove.textContent = "×";
+  remove.title = "Remove snippet";
+  remove.addEventListener("click", () => item.remove());
+
+  item.append(phrase, text, remove);
+  els.snippetList.append(item);
+  return phrase;
+}
+
+function readSnippets() {
+  return [...els.snippetList.querySelectorAll("li")]
+    .map((item) => ({
+      phrase: item.querySelector(".snippet-phrase").value.trim(),
+      text: item.querySelector(".snippet-text").value,
+    }))
+    .filter((snippet) => snippet.phrase && snippet.text.trim());
+}
+
+async function importSnippets() {
+  const path = els.snippetFile.value.trim();
+  if (!path) {
+    return;
+  }
+  const snippets = await invoke("import_snippets", { path });
+  state.settings.snippets = snippets;
+  renderSnippets(snippets);
+  setStatus(`Imported snippets from ${path}`);
+}
+
+async function exportSnippets() {
+  const path = els.snippetFile.value.trim();
+  if (!path) {
+    return;
+  }
+  await invoke("export_snippets", { path });
+  setStatus(`Snippets exported to ${path}`);
 }
 
 async function loadMicrophones(selected) {
@@ -63,6 +284,115 @@ async function loadMicrophones(selected) {
   els.microphone.value = selected || "";
 }
 
+function formatBytes(bytes) {
+  const mb = bytes / (1024 * 1024);
+  return mb >= 1024 ? `${(mb / 1024).toFixed(1)} GB` : `${Math.round(mb)} MB`;
+}
+
+async function loadModels(selected) {
+  const models = await invoke("list_models").catch(() => []);
+  const partialSelected = els.partialModel.value || state.settings?.partial_model || "";
+  els.model.innerHTML = "";
+  els.partialModel.innerHTML = "";
+  const sameModel = document.createElement("option");
+  sameModel.value = "";
+  sameModel.textContent = "Same as transcription model";
+  els.partialModel.append(sameModel);
+
+  for (const model of models) {
+    const option = document.createElement("option");
+    option.value = model.id;
+    const language = model.multilingual ? "multilingual" : "English";
+    option.textContent = `${model.id} (${language}, ${model.quantization}, ${formatBytes(model.size_bytes)})`;
+    option.disabled = model.status === "corrupt" || model.status === "partial";
+    els.model.append(option);
+    els.partialModel.append(option.cloneNode(true));
+  }
+  renderModelList(models);
+
+  if (!models.some((model) => model.id === selected)) {
+    const missing = document.createElement("option");
+    missing.value = selected;
+    missing.textContent = `${selected} (not installed)`;
+    els.model.append(missing);
+  }
+
+  els.model.value = selected;
+  els.partialModel.value = partialSelected;
+}
+
+function renderModelList(models) {
+  els.modelList.innerHTML = "";
+
+  for (const model of models) {
+    const item = document.createElement("li");
+    const label = document.createElement("span");
+    label.textContent = `${model.id} · ${model.status}`;
+    label.title = model.error ?? "";
+    label.classList.add(`status-${model.status}`);
+    item.append(label);
+
+    const verify = document.createElement("button");
+    verify.type = "button";
+    verify.textContent = "Verify";
+    verify.addEventListener("click", async () => {
+      setStatus(`Verifying ${model.id}…`);
+      try {
+        const result = await invoke("verify_model", { id: model.id });
+        setStatus(result.error ? `${model.id}: ${result.error}` : `${model.id}: ${result.status}`);
+        await loadModels(els.model.value);
+      } catch (error) {
+        setStatus(String(error));
+      }
+    });
+
+    const remove = document.createElement("button");
+    remove.type = "button";
+    remove.textContent = "Delete";
+    remove.addEventListener("click", async () => {
+      try {
+        await invoke("delete_model", { id: model.id });
+        await loadModels(els.model.value);
+        setStatus(`Deleted ${model.id}`);
+      } catch (error) {
+        setStatus(String(error));
+      }
+    });
+
+    item.append(verify, remove);
+    els.modelList.append(item);
+  }
+}
+
+async function importModel() {
+  const path = els.modelImportPath.value.trim();
+  if (!path) {
+    return;
+  }
+  setStatus("Importing model…");
+  const sha256 = els.modelImportSha256.value.trim() || null;
+  const model = await invoke("import_model", { path, sha256 });
+  els.modelImportPath.value = "";
+  els.modelImportSha256.value = "";
+  await loadModels(els.model.value);
+  setStatus(`Imported ${model.id} (${model.status})`);
+}
+
+async function transcribeFile() {
+  const input = els.batchInput.value.trim();
+  if (!input) {
+    return;
+  }
+  setStatus("Transcribing…");
+  els.batchTranscribe.disabled = true;
+  try {
+    const output = await invoke("transcribe_file", { input, format: els.batchFormat.value });
+    setStatus(`Transcript written to ${output}`);
+  } finally {
+    els.batchTranscribe.disabled = false;
+  }
+}
+
 async function saveSettings() {
   const next = currentFormSettings();
   await invoke("update_settings", { next });
@@ -86,6 +416,13 @@ async function bootstrap() {
   const settings = await invoke("get_settings");
   hydrateForm(settings);
   await loadMicrophones(settings.microphone);
+  await loadModels(settings.model);
+  for (const entry of await invoke("get_health").catch(() => [])) {
+    health.set(entry.component, entry.health);
+  }
+  decodeStats = await invoke("get_diagnostics").catch(() => null);
+  els.commandsPath.value = await invoke("get_commands_path").catch(() => "");
+  renderHealth();
 
   els.save.addEventListener("click", async () => {
     try {
@@ -95,6 +432,70 @@ async function bootstrap() {
     }
   });
 
+  els.modelImport.addEventListener("click", async () => {
+    try {
+      await importModel();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.batchTranscribe.addEventListener("click", async () => {
+    try {
+      await transcribeFile();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.reloadCommands.addEventListener("click", async () => {
+    try {
+      await invoke("reload_commands");
+      setStatus("Commands reloaded");
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.vocabularyAdd.addEventListener("click", async () => {
+    try {
+      await addVocabularyTerm();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.vocabularyTerm.addEventListener("keydown", async (event) => {
+    if (event.key !== "Enter") {
+      return;
+    }
+    try {
+      await addVocabularyTerm();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.snippetAdd.addEventListener("click", () => {
+    addSnippetRow({ phrase: "", text: "" }).focus();
+  });
+
+  els.snippetImport.addEventListener("click", async () => {
+    try {
+      await importSnippets();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
+  els.snippetExport.addEventListener("click", async () => {
+    try {
+      await exportSnippets();
+    } catch (error) {
+      setStatus(String(error));
+    }
+  });
+
   els.requestPermissions.addEventListener("click", async () => {
     try {
       await requestPermissions();
@@ -103,6 +504,14 @@ async function bootstrap() {
     }
   });
 
+  els.modelLoadPolicy.addEventListener("change", () => {
+    els.modelIdleMinutes.disabled = els.modelLoadPolicy.value !== "unload_when_idle";
+  });
+
+  els.carryContext.addEventListener("change", () => {
+    els.contextWindowSeconds.disabled = !els.carryContext.checked;
+  });
+
   els.sensitivity.addEventListener("input", () => {
     els.sensitivityValue.value = Number(els.sensitivity.value).toFixed(2);
   });
@@ -112,6 +521,26 @@ async function bootstrap() {
     els.permissionsNotice.classList.toggle("hidden", !missing);
   });
 
+  listen("model-load", ({ payload }) => {
+    const messages = {
+      loading: `Loading ${payload.model}…`,
+      ready: `${payload.model} loaded; switching after this dictation`,
+      active: `Now using ${payload.model}`,
+      failed: `Could not load ${payload.model}: ${payload.error}`,
+    };
+    setStatus(messages[payload.stage]);
+  });
+
+  listen("engine-health", ({ payload }) => {
+    health.set(payload.component, payload.health);
+    renderHealth();
+  });
+
+  listen("engine-diagnostics", ({ payload }) => {
+    decodeStats = payload;
+    renderHealth();
+  });
+
   listen("engine-error", ({ payload }) => {
     setStatus(payload);
   });
@@ -8,6 +8,17 @@ SAMPLE_INTERVAL="${3:-1}"
 BINARY="${LUMI_BINARY:-$ROOT_DIR/target/debug/lumitype}"
 LOG_FILE="${LUMI_BENCH_LOG:-/tmp/lumitype-bench.log}"
 ATTACH_EXISTING="${LUMI_ATTACH_EXISTING:-0}"
+# Optional: always | on_first_dictation | unload_when_idle. The settings file is
+# patched for the run and restored afterwards.
+POLICY="${LUMI_BENCH_POLICY:-}"
+IDLE_MINUTES="${LUMI_BENCH_IDLE_MINUTES:-}"
+if [[ "$(uname)" == "Darwin" ]]; then
+  DEFAULT_SETTINGS="$HOME/Library/Application Support/com.LumiType.LumiType/settings.json"
+else
+  DEFAULT_SETTINGS="${XDG_CONFIG_HOME:-$HOME/.config}/lumitype/settings.json"
+fi
+SETTINGS_FILE="${LUMI_SETTINGS:-$DEFAULT_SETTINGS}"
+SETTINGS_BACKUP=""
 
 if [[ ! -x "$BINARY" ]]; then
   echo "Binary not found at $BINARY"
@@ -23,6 +34,9 @@ LAUNCH_MODE="binary"
 OWN_PROCESS=1
 
 cleanup() {
+  if [[ -n "$SETTINGS_BACKUP" ]]; then
+    mv "$SETTINGS_BACKUP" "$SETTINGS_FILE"
+  fi
   if [[ "$OWN_PROCESS" -ne 1 ]]; then
     return
   fi
@@ -62,6 +76,28 @@ launch_via_tauri_dev() {
   return 1
 }
 
+if [[ -n "$POLICY" || -n "$IDLE_MINUTES" ]]; then
+  if [[ "$ATTACH_EXISTING" == "1" ]]; then
+    echo "LUMI_BENCH_POLICY needs a fresh launch; unset LUMI_ATTACH_EXISTING." >&2
+    exit 1
+  fi
+  if ! grep -q '"model_load_policy"' "$SETTINGS_FILE" 2>/dev/null; then
+    echo "No model_load_policy in $SETTINGS_FILE; save settings once from the app first." >&2
+    exit 1
+  fi
+  SETTINGS_BACKUP="$(mktemp)"
+  cp "$SETTINGS_FILE" "$SETTINGS_BACKUP"
+  if [[ -n "$POLICY" ]]; then
+    sed -E -i.bak "s/\"model_load_policy\": *\"[a-z_]+\"/\"model_load_policy\": \"$POLICY\"/" "$SETTINGS_FILE"
+  fi
+  if [[ -n "$IDLE_MINUTES" ]]; then
+    sed -E -i.bak "s/\"model_idle_minutes\": *[0-9]+/\"model_idle_minutes\": $IDLE_MINUTES/" "$SETTINGS_FILE"
+  fi
+  rm -f "$SETTINGS_FILE.bak"
+fi
+ACTIVE_POLICY="$(sed -nE 's/.*"model_load_policy": *"([a-z_]+)".*/\1/p' "$SETTINGS_FILE" 2>/dev/null | head -n 1)"
+ACTIVE_IDLE_MINUTES="$(sed -nE 's/.*"model_idle_minutes": *([0-9]+).*/\1/p' "$SETTINGS_FILE" 2>/dev/null | head -n 1)"
+
 echo "Starting LumiType benchmark for ${SAMPLE_SECONDS}s (interval ${SAMPLE_INTERVAL}s)"
 
 if [[ "$ATTACH_EXISTING" == "1" ]]; then
@@ -89,6 +125,8 @@ fi
   echo "launch_mode: $LAUNCH_MODE"
   echo "sample_seconds: $SAMPLE_SECONDS"
   echo "sample_interval_seconds: $SAMPLE_INTERVAL"
+  echo "model_load_policy: ${ACTIVE_POLICY:-unknown}"
+  echo "model_idle_minutes: ${ACTIVE_IDLE_MINUTES:-unknown}"
   echo
   echo "timestamp,pcpu,rss_kb,vsz_kb"
 
@@ -111,7 +149,7 @@ fi
 {
   echo
   echo "summary"
-  awk -F',' 'NR>8 && NF==4 {cpu+=$2; rss+=$3; n+=1; if($2>cpu_max) cpu_max=$2; if($3>rss_max) rss_max=$3} END {if(n>0) {printf("samples=%d\navg_cpu=%.2f\nmax_cpu=%.2f\navg_rss_mb=%.2f\nmax_rss_mb=%.2f\n", n, cpu/n, cpu_max, (rss/n)/1024, rss_max/1024)} else {print "no_samples_collected"}}' "$OUT_FILE"
+  awk -F',' 'NR>10 && NF==4 {cpu+=$2; rss+=$3; n+=1; last=$3; if($2>cpu_max) cpu_max=$2; if($3>rss_max) rss_max=$3} END {if(n>0) {printf("samples=%d\navg_cpu=%.2f\nmax_cpu=%.2f\navg_rss_mb=%.2f\nmax_rss_mb=%.2f\nfinal_rss_mb=%.2f\n", n, cpu/n, cpu_max, (rss/n)/1024, rss_max/1024, last/1024)} else {print "no_samples_collected"}}' "$OUT_FILE"
 } >> "$OUT_FILE"
 
 echo "Benchmark written to $OUT_FILE"
@@ -0,0 +1,391 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    transcriber::{self, FinalTranscript, TimedWord, TranscriberConfig},
+    vad::resample_mono_to_16k,
+};
+
+/// Longest subtitle cue; longer segments are split between words.
+const MAX_CUE_CHARS: usize = 84;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptFormat {
+    Text,
+    Json,
+    Srt,
+    Vtt,
+}
+
+impl TranscriptFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscriptFormat::Text => "txt",
+            TranscriptFormat::Json => "json",
+            TranscriptFormat::Srt => "srt",
+            TranscriptFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// Transcribes the recording at `input` and writes it to `output` in `format`.
+pub fn transcribe_file(
+    config: &TranscriberConfig,
+    input: &Path,
+    output: &Path,
+    format: TranscriptFormat,
+) -> Result<FinalTranscript> {
+    let samples = read_audio(input)?;
+    let transcript = transcriber::transcribe_recording(config, &samples)
+        .with_context(|| format!("failed to transcribe {}", input.display()))?;
+    fs::write(output, render(&transcript, format)?)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(transcript)
+}
+
+/// Reads a WAV file (PCM 8/16/24/32-bit or 32-bit float, any channel count
+/// and rate) or raw 16 kHz mono s16le `.pcm`/`.raw` audio as 16 kHz mono.
+pub fn read_audio(path: &Path) -> Result<Vec<i16>> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "pcm" | "raw" => Ok(bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()),
+        _ => decode_wav(&bytes).with_context(|| format!("unsupported audio in {}", path.display())),
+    }
+}
+
+pub fn render(transcript: &FinalTranscript, format: TranscriptFormat) -> Result<String> {
+    Ok(match format {
+        TranscriptFormat::Text => format!("{}\n", transcript.text),
+        TranscriptFormat::Json => serde_json::to_string_pretty(transcript)?,
+        TranscriptFormat::Srt => {
+            let mut out = String::new();
+            for (index, (start_ms, end_ms, text)) in cues(transcript).into_iter().enumerate() {
+                let _ = writeln!(out, "{}", index + 1);
+                let _ = writeln!(
+                    out,
+                    "{} --> {}",
+                    timestamp(start_ms, ','),
+                    timestamp(end_ms, ',')
+                );
+                let _ = writeln!(out, "{text}\n");
+            }
+            out
+        }
+        TranscriptFormat::Vtt => {
+            let mut out = String::from("WEBVTT\n\n");
+            for (start_ms, end_ms, text) in cues(transcript) {
+                let _ = writeln!(
+                    out,
+                    "{} --> {}",
+                    timestamp(start_ms, '.'),
+                    timestamp(end_ms, '.')
+                );
+                let _ = writeln!(out, "{text}\n");
+            }
+            out
+        }
+    })
+}
+
+/// Subtitle cues: the transcript's segments, split between words where a
+/// segment is too long to read at once. A word belongs to the last segment
+/// that starts at or before it, since whisper's word times can run a little
+/// past their segment's end.
+fn cues(transcript: &FinalTranscript) -> Vec<(u64, u64, String)> {
+    let mut by_segment = vec![Vec::new(); transcript.segments.len()];
+    for word in &transcript.words {
+        let starts_before = transcript
+            .segments
+            .partition_point(|segment| segment.start_ms <= word.start_ms);
+        if let Some(words) = by_segment.get_mut(starts_before.saturating_sub(1)) {
+            words.push(word);
+        }
+    }
+
+    let mut cues = Vec::new();
+    for (segment, words) in transcript.segments.iter().zip(by_segment) {
+        if words.is_empty() {
+            cues.push((segment.start_ms, segment.end_ms, segment.text.clone()));
+            continue;
+        }
+
+        let mut current: Vec<&TimedWord> = Vec::new();
+        let mut len = 0;
+        for word in words {
+            if !current.is_empty() && len + 1 + word.text.len() > MAX_CUE_CHARS {
+                cues.push(cue(&current));
+                current.clear();
+                len = 0;
+            }
+            len += word.text.len() + usize::from(!current.is_empty());
+            current.push(word);
+        }
+        cues.push(cue(&current));
+    }
+    cues
+}
+
+fn cue(words: &[&TimedWord]) -> (u64, u64, String) {
+    let text = words
+        .iter()
+        .map(|word| word.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (words[0].start_ms, words[words.len() - 1].end_ms, text)
+}
+
+fn timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1_000 % 60,
+        ms % 1_000
+    )
+}
+
+fn decode_wav(bytes: &[u8]) -> Result<Vec<i16>> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        bail!("not a WAV file");
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = u16::from_le_bytes([body[0], body[1]]);
+                // WAVE_FORMAT_EXTENSIBLE keeps the real tag in its sub-format GUID.
+                if tag == 0xFFFE && body.len() >= 26 {
+                    tag = u16::from_le_bytes([body[24], body[25]]);
+                }
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let rate = u32::from_le_bytes(body[4..8].try_into()?);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = Some((tag, channels, rate, bits));
+            }
+            b"data" => {
+                let Some((tag, channels, rate, bits)) = format else {
+                    bail!("data chunk before fmt chunk");
+                };
+                if channels == 0 {
+                    bail!("WAV file has no channels");
+                }
+                let mono = downmix(&pcm_samples(body, tag, bits)?, channels as usize);
+                return Ok(resample_mono_to_16k(&mono, rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        offset += 8 + size + size % 2;
+    }
+    bail!("WAV file has no data chunk")
+}
+
+fn pcm_samples(data: &[u8], tag: u16, bits: u16) -> Result<Vec<i16>> {
+    Ok(match (tag, bits) {
+        (1, 8) => data.iter().map(|byte| (*byte as i16 - 128) << 8).collect(),
+        (1, 16) => data
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect(),
+        (1, 24) => data
+            .chunks_exact(3)
+            .map(|triple| i16::from_le_bytes([triple[1], triple[2]]))
+            .collect(),
+        (1, 32) => data
+            .chunks_exact(4)
+            .map(|quad| i16::from_le_bytes([quad[2], quad[3]]))
+            .collect(),
+        (3, 32) => data
+            .chunks_exact(4)
+            .map(|quad| {
+                let sample = f32::from_le_bytes([quad[0], quad[1], quad[2], quad[3]]);
+                (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+            })
+            .collect(),
+        _ => bail!("unsupported WAV encoding (format {tag}, {bits}-bit)"),
+    })
+}
+
+fn downmix(samples: &[i16], channels: usize) -> Vec<i16> {
+    if channels == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            (frame.iter().map(|sample| *sample as i32).sum::<i32>() / channels as i32) as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_audio, render, TranscriptFormat};
+    use crate::core::transcriber::{FinalTranscript, TimedWord, TranscriptSegment};
+
+    fn wav(channels: u16, rate: u32, samples: &[i16]) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&rate.to_le_bytes());
+        bytes.extend_from_slice(&(rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend_from_slice(&(channels * 2).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        // An odd-sized chunk the reader has to skip, padding included.
+        bytes.extend_from_slice(b"LIST");
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2, 3, 0]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_stereo_wav_as_16k_mono() {
+        let dir = std::env::temp_dir().join(format!("lumitype-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let stereo = [1_000i16, 3_000].repeat(48_000);
+        let path = dir.join("meeting.wav");
+        std::fs::write(&path, wav(2, 48_000, &stereo)).unwrap();
+        let samples = read_audio(&path).unwrap();
+        assert!((samples.len() as i64 - 16_000).abs() < 10);
+        assert!(samples.iter().all(|sample| *sample == 2_000));
+
+        let raw = dir.join("meeting.pcm");
+        std::fs::write(&raw, [0x10, 0x00, 0xff, 0xff]).unwrap();
+        assert_eq!(read_audio(&raw).unwrap(), vec![16, -1]);
+
+        std::fs::write(&path, b"not audio").unwrap();
+        assert!(read_audio(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn transcript() -> FinalTranscript {
+        let word = |text: &str, start_ms: u64, end_ms: u64| TimedWord {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            probability: 1.0,
+        };
+        FinalTranscript {
+            text: "Hello there. Welcome back.".to_string(),
+            language: Some("en".to_string()),
+            words: vec![
+                word("Hello", 0, 400),
+                word("there.", 450, 900),
+                word("Welcome", 3_661_000, 3_661_400),
+                word("back.", 3_661_500, 3_662_050),
+            ],
+            segments: vec![
+                TranscriptSegment {
+                    text: "Hello there.".to_string(),
+                    start_ms: 0,
+                    end_ms: 900,
+                    probability: 1.0,
+                },
+                TranscriptSegment {
+                    text: "Welcome back.".to_string(),
+                    start_ms: 3_661_000,
+                    end_ms: 3_662_050,
+                    probability: 1.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn renders_subtitles_and_text() {
+        let transcript = transcript();
+        assert_eq!(
+            render(&transcript, TranscriptFormat::Srt).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,900\nHello there.\n\n\
+             2\n01:01:01,000 --> 01:01:02,050\nWelcome back.\n\n"
+        );
+        assert_eq!(
+            render(&transcript, TranscriptFormat::Vtt).unwrap(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:00.900\nHello there.\n\n\
+             01:01:01.000 --> 01:01:02.050\nWelcome back.\n\n"
+        );
+        assert_eq!(
+            render(&transcript, TranscriptFormat::Text).unwrap(),
+            "Hello there. Welcome back.\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&transcript, TranscriptFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["segments"][1]["start_ms"], 3_661_000);
+    }
+
+    #[test]
+    fn words_running_past_their_segment_stay_in_its_cue() {
+        let mut transcript = transcript();
+        transcript.words[1].end_ms = 930;
+        transcript.words[3].end_ms = 3_662_080;
+        assert_eq!(
+            render(&transcript, TranscriptFormat::Srt).unwrap(),
+            "1\n00:00:00,000 --> 00:00:00,930\nHello there.\n\n\
+             2\n01:01:01,000 --> 01:01:02,080\nWelcome back.\n\n"
+        );
+    }
+
+    #[test]
+    fn long_segments_are_split_into_readable_cues() {
+        let words = (0..40)
+            .map(|index| TimedWord {
+                text: format!("word{index}"),
+                start_ms: index * 300,
+                end_ms: index * 300 + 250,
+                probability: 1.0,
+            })
+            .collect::<Vec<_>>();
+        let text = words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let transcript = FinalTranscript {
+            segments: vec![TranscriptSegment {
+                text: text.clone(),
+                start_ms: 0,
+                end_ms: 39 * 300 + 250,
+                probability: 1.0,
+            }],
+            text,
+            language: None,
+            words,
+        };
+
+        let vtt = render(&transcript, TranscriptFormat::Vtt).unwrap();
+        let lines = vtt
+            .lines()
+            .filter(|line| line.starts_with("word"))
+            .collect::<Vec<_>>();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.len() <= 84));
+        assert_eq!(lines.join(" "), transcript.text);
+    }
+}
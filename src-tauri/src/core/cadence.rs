@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Partial decode interval when inference keeps up with real time.
+const BASE_INTERVAL: Duration = Duration::from_millis(350);
+/// Slowest partial cadence; slower machines still see some live text.
+const MAX_INTERVAL: Duration = Duration::from_secs(2);
+/// Weight of the newest measurement in the running real-time factor.
+const RTF_SMOOTHING: f32 = 0.3;
+
+/// Decode speed and cadence of the live transcriber, for diagnostics.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DecodeStats {
+    /// Seconds of inference per second of audio decoded, smoothed over the
+    /// recent partial decodes; `None` until the first one.
+    pub real_time_factor: Option<f32>,
+    pub decode_interval_ms: u64,
+    /// Partial decodes run in the last session.
+    pub partial_decodes: u32,
+    /// Partial decodes skipped in the last session because nothing but
+    /// silence arrived since the previous one.
+    pub skipped_silent: u32,
+}
+
+/// Decides when the next partial decode runs. The interval stretches by the
+/// measured real-time factor when inference is slower than real time, and a
+/// decode only runs when speech arrived since the previous one.
+#[derive(Debug)]
+pub struct DecodeCadence {
+    real_time_factor: Option<f32>,
+    last_decode_at: Instant,
+    speech_since_decode: bool,
+    partial_decodes: u32,
+    skipped_silent: u32,
+}
+
+impl DecodeCadence {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            real_time_factor: None,
+            last_decode_at: now,
+            speech_since_decode: false,
+            partial_decodes: 0,
+            skipped_silent: 0,
+        }
+    }
+
+    /// Starts a session. The measured speed carries over; it belongs to the
+    /// machine and model, not the session.
+    pub fn begin(&mut self, now: Instant) {
+        self.last_decode_at = now;
+        self.speech_since_decode = false;
+        self.partial_decodes = 0;
+        self.skipped_silent = 0;
+    }
+
+    /// Forgets the measured speed, e.g. after switching models.
+    pub fn reset_speed(&mut self) {
+        self.real_time_factor = None;
+    }
+
+    pub fn note_audio(&mut self, speech: bool) {
+        self.speech_since_decode |= speech;
+    }
+
+    pub fn interval(&self) -> Duration {
+        match self.real_time_factor {
+            Some(rtf) if rtf > 1.0 => BASE_INTERVAL.mul_f32(rtf).min(MAX_INTERVAL),
+            _ => BASE_INTERVAL,
+        }
+    }
+
+    /// Whether a partial decode should run now. A due slot with only silence
+    /// since the last decode is skipped and counted.
+    pub fn decode_due(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_decode_at) < self.interval() {
+            return false;
+        }
+        if !self.speech_since_decode {
+            self.last_decode_at = now;
+            self.skipped_silent += 1;
+            return false;
+        }
+        true
+    }
+
+    /// Records a partial decode that took `cost` for `audio` worth of samples.
+    pub fn record_decode(&mut self, now: Instant, cost: Duration, audio: Duration) {
+        self.last_decode_at = now;
+        self.speech_since_decode = false;
+        self.partial_decodes += 1;
+        if audio.is_zero() {
+            return;
+        }
+        let sample = cost.as_secs_f32() / audio.as_secs_f32();
+        self.real_time_factor = Some(match self.real_time_factor {
+            Some(rtf) => rtf + (sample - rtf) * RTF_SMOOTHING,
+            None => sample,
+        });
+    }
+
+    pub fn stats(&self) -> DecodeStats {
+        DecodeStats {
+            real_time_factor: self.real_time_factor,
+            decode_interval_ms: self.interval().as_millis() as u64,
+            partial_decodes: self.partial_decodes,
+            skipped_silent: self.skipped_silent,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{DecodeCadence, BASE_INTERVAL, MAX_INTERVAL};
+
+    #[test]
+    fn backs_off_when_slower_than_real_time() {
+        let start = Instant::now();
+        let mut cadence = DecodeCadence::new(start);
+        assert_eq!(cadence.interval(), BASE_INTERVAL);
+
+        cadence.record_decode(start, Duration::from_millis(500), Duration::from_secs(1));
+        assert_eq!(cadence.interval(), BASE_INTERVAL);
+
+        let mut now = start;
+        for _ in 0..10 {
+            now += Duration::from_secs(1);
+            cadence.record_decode(now, Duration::from_secs(3), Duration::from_secs(2));
+        }
+        let rtf = cadence.stats().real_time_factor.unwrap();
+        assert!((rtf - 1.5).abs() < 0.05, "{rtf}");
+        assert!(cadence.interval() > BASE_INTERVAL);
+
+        for _ in 0..10 {
+            cadence.record_decode(now, Duration::from_secs(20), Duration::from_secs(2));
+        }
+        assert_eq!(cadence.interval(), MAX_INTERVAL);
+    }
+
+    #[test]
+    fn skips_decodes_after_silence_only() {
+        let start = Instant::now();
+        let mut cadence = DecodeCadence::new(start);
+        cadence.begin(start);
+
+        cadence.note_audio(false);
+        assert!(!cadence.decode_due(start + Duration::from_millis(100)));
+        assert!(!cadence.decode_due(start + Duration::from_millis(400)));
+        assert_eq!(cadence.stats().skipped_silent, 1);
+
+        cadence.note_audio(true);
+        assert!(!cadence.decode_due(start + Duration::from_millis(500)));
+        let due = start + Duration::from_millis(800);
+        assert!(cadence.decode_due(due));
+        cadence.record_decode(due, Duration::from_millis(100), Duration::from_secs(1));
+
+        cadence.note_audio(false);
+        assert!(!cadence.decode_due(due + Duration::from_secs(1)));
+        let stats = cadence.stats();
+        assert_eq!((stats.partial_decodes, stats.skipped_silent), (1, 2));
+    }
+}
@@ -0,0 +1,279 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use enigo::Key;
+use serde::{Deserialize, Serialize};
+
+/// Spoken phrases mapped to key presses, kept in a user-editable
+/// `commands.json` next to the settings file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CommandGrammar {
+    /// Said before a command during normal dictation ("command, select all").
+    /// Empty leaves only the command hotkey.
+    pub trigger: String,
+    pub commands: Vec<CommandSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandSpec {
+    pub phrases: Vec<String>,
+    /// Chords pressed in order, such as `"Cmd+Shift+T"` or `"Return"`.
+    pub keys: Vec<String>,
+}
+
+impl Default for CommandGrammar {
+    fn default() -> Self {
+        let command = |phrases: &[&str], keys: &[&str]| CommandSpec {
+            phrases: phrases.iter().map(|phrase| phrase.to_string()).collect(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+        Self {
+            trigger: "command".to_string(),
+            commands: vec![
+                command(&["press enter", "enter", "press return"], &["Return"]),
+                command(&["tab", "press tab"], &["Tab"]),
+                command(&["escape", "press escape"], &["Escape"]),
+                command(&["backspace", "press backspace"], &["Backspace"]),
+                command(&["select all"], &["Cmd+A"]),
+                command(&["copy", "copy that"], &["Cmd+C"]),
+                command(&["cut", "cut that"], &["Cmd+X"]),
+                command(&["paste", "paste that"], &["Cmd+V"]),
+                command(&["undo"], &["Cmd+Z"]),
+                command(&["redo"], &["Cmd+Shift+Z"]),
+                command(&["save", "save file"], &["Cmd+S"]),
+                command(&["new tab"], &["Cmd+T"]),
+                command(&["close tab"], &["Cmd+W"]),
+                command(&["next tab"], &["Ctrl+Tab"]),
+                command(&["previous tab"], &["Ctrl+Shift+Tab"]),
+            ],
+        }
+    }
+}
+
+/// Keys held down while `key` is pressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChord {
+    pub modifiers: Vec<Key>,
+    pub key: Key,
+}
+
+impl KeyChord {
+    /// Parses `"Cmd+Shift+T"`-style chords; names are case-insensitive.
+    pub fn parse(text: &str) -> Result<Self> {
+        let parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let Some((key, modifiers)) = parts.split_last() else {
+            bail!("empty key chord");
+        };
+        let modifiers = modifiers
+            .iter()
+            .map(|name| modifier_key(name).with_context(|| format!("unknown modifier {name:?}")))
+            .collect::<Result<Vec<_>>>()?;
+        let key = named_key(key).with_context(|| format!("unknown key {key:?} in {text:?}"))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+fn modifier_key(name: &str) -> Option<Key> {
+    match name.to_lowercase().as_str() {
+        "cmd" | "command" | "meta" | "super" | "win" => Some(Key::Meta),
+        "ctrl" | "control" => Some(Key::Control),
+        "alt" | "option" | "opt" => Some(Key::Alt),
+        "shift" => Some(Key::Shift),
+        _ => None,
+    }
+}
+
+fn named_key(name: &str) -> Option<Key> {
+    let lower = name.to_lowercase();
+    let key = match lower.as_str() {
+        "return" | "enter" => Key::Return,
+        "tab" => Key::Tab,
+        "escape" | "esc" => Key::Escape,
+        "space" => Key::Space,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => {
+            let mut chars = lower.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Key::Unicode(ch),
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+/// A grammar with its phrases normalized and its chords parsed, ready to
+/// match transcripts against.
+#[derive(Debug, Clone, Default)]
+pub struct CommandSet {
+    trigger: String,
+    commands: Vec<(String, Vec<KeyChord>)>,
+}
+
+impl CommandSet {
+    /// Fails on the first chord that does not parse, naming its phrase.
+    pub fn compile(grammar: &CommandGrammar) -> Result<Self> {
+        let mut commands = Vec::new();
+        for spec in &grammar.commands {
+            let label = spec.phrases.first().map(String::as_str).unwrap_or("");
+            let chords = spec
+                .keys
+                .iter()
+                .map(|key| KeyChord::parse(key))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("command {label:?}"))?;
+            for phrase in &spec.phrases {
+                let phrase = normalize(phrase);
+                if !phrase.is_empty() {
+                    commands.push((phrase, chords.clone()));
+                }
+            }
+        }
+        Ok(Self {
+            trigger: normalize(&grammar.trigger),
+            commands,
+        })
+    }
+
+    /// The rest of `transcript` when it starts with the trigger phrase.
+    pub fn strip_trigger(&self, transcript: &str) -> Option<String> {
+        if self.trigger.is_empty() {
+            return None;
+        }
+        let spoken = normalize(transcript);
+        let rest = spoken.strip_prefix(&self.trigger)?;
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+        Some(rest.trim().to_string())
+    }
+
+    /// The chords for a spoken command, ignoring case, punctuation and a
+    /// polite "please".
+    pub fn lookup(&self, spoken: &str) -> Option<&[KeyChord]> {
+        let spoken = normalize(spoken);
+        let spoken = spoken.strip_prefix("please ").unwrap_or(&spoken);
+        let spoken = spoken.strip_suffix(" please").unwrap_or(spoken);
+        self.commands
+            .iter()
+            .find(|(phrase, _)| phrase == spoken)
+            .map(|(_, chords)| chords.as_slice())
+    }
+}
+
+/// Lowercase words without whisper's punctuation: "Select-all." and
+/// "select all" compare equal.
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|ch: char| !ch.is_alphanumeric() && ch != '\'')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads the grammar, writing the default one first when the file is missing
+/// so there is something to edit.
+pub fn load_grammar(path: &Path) -> Result<CommandGrammar> {
+    if !path.exists() {
+        let grammar = CommandGrammar::default();
+        let content =
+            serde_json::to_string_pretty(&grammar).context("failed to encode commands")?;
+        fs::write(path, content).context("failed to write commands file")?;
+        return Ok(grammar);
+    }
+    let content = fs::read_to_string(path).context("failed to read commands file")?;
+    serde_json::from_str(&content).context("failed to parse commands file")
+}
+
+#[cfg(test)]
+mod tests {
+    use enigo::Key;
+
+    use super::{CommandGrammar, CommandSet, KeyChord};
+
+    #[test]
+    fn chords_parse_modifiers_and_keys() {
+        let chord = KeyChord::parse("Cmd+Shift+T").unwrap();
+        assert_eq!(chord.modifiers, vec![Key::Meta, Key::Shift]);
+        assert_eq!(chord.key, Key::Unicode('t'));
+        assert_eq!(KeyChord::parse("return").unwrap().key, Key::Return);
+        assert_eq!(
+            KeyChord::parse("ctrl + pagedown").unwrap().key,
+            Key::PageDown
+        );
+        assert!(KeyChord::parse("Hyper+A").is_err());
+        assert!(KeyChord::parse("Cmd+Enterr").is_err());
+    }
+
+    #[test]
+    fn commands_match_regardless_of_case_and_punctuation() {
+        let commands = CommandSet::compile(&CommandGrammar::default()).unwrap();
+        let cases = [
+            ("Press enter.", Some("Return")),
+            ("Select all!", Some("Cmd+A")),
+            ("select-all", Some("Cmd+A")),
+            ("Copy that, please.", Some("Cmd+C")),
+            ("  TAB  ", Some("Tab")),
+            ("Redo.", Some("Cmd+Shift+Z")),
+            ("select everything", None),
+            ("", None),
+        ];
+        for (spoken, keys) in cases {
+            let expected = keys.map(|keys| vec![KeyChord::parse(keys).unwrap()]);
+            assert_eq!(
+                commands.lookup(spoken).map(<[KeyChord]>::to_vec),
+                expected,
+                "{spoken:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn trigger_phrase_starts_a_command() {
+        let commands = CommandSet::compile(&CommandGrammar::default()).unwrap();
+        assert_eq!(
+            commands.strip_trigger("Command, select all.").as_deref(),
+            Some("select all")
+        );
+        assert_eq!(commands.strip_trigger("Command.").as_deref(), Some(""));
+        assert_eq!(commands.strip_trigger("Commanding officer"), None);
+        assert_eq!(commands.strip_trigger("The command line"), None);
+
+        let grammar = CommandGrammar {
+            trigger: String::new(),
+            ..CommandGrammar::default()
+        };
+        let without_trigger = CommandSet::compile(&grammar).unwrap();
+        assert_eq!(without_trigger.strip_trigger("command tab"), None);
+    }
+
+    #[test]
+    fn invalid_keys_name_their_command() {
+        let mut grammar = CommandGrammar::default();
+        grammar.commands[0].keys = vec!["Cmd+Nope".to_string()];
+        let error = CommandSet::compile(&grammar).unwrap_err();
+        assert!(format!("{error:#}").contains("press enter"), "{error:#}");
+    }
+}
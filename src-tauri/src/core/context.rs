@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+/// Process id of the app that had keyboard focus, used to tell whether a new
+/// session types into the same place as the previous one.
+pub type FocusedApp = i32;
+
+/// The frontmost app, or `None` where it cannot be determined.
+pub fn focused_app() -> Option<FocusedApp> {
+    #[cfg(target_os = "macos")]
+    {
+        use objc::{class, msg_send, runtime::Object, sel, sel_impl};
+
+        // SAFETY: plain AppKit getters; a null app means nothing is frontmost.
+        unsafe {
+            let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let app: *mut Object = msg_send![workspace, frontmostApplication];
+            if app.is_null() {
+                return None;
+            }
+            let pid: i32 = msg_send![app, processIdentifier];
+            Some(pid)
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// Tail of a finished session, offered as prompt context to the next one so
+/// a continuation keeps the same spelling and sentence flow.
+#[derive(Debug, Clone)]
+pub struct SessionContext {
+    text: String,
+    ended_at: Instant,
+    focus: Option<FocusedApp>,
+}
+
+impl SessionContext {
+    pub fn new(text: String, ended_at: Instant, focus: Option<FocusedApp>) -> Self {
+        Self {
+            text,
+            ended_at,
+            focus,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether a session starting at `now` in `focus` continues this one:
+    /// it starts within `window` and types into the same app. Where focus
+    /// cannot be determined only the window applies.
+    pub fn continues(&self, now: Instant, focus: Option<FocusedApp>, window: Duration) -> bool {
+        now.saturating_duration_since(self.ended_at) <= window && focus == self.focus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::SessionContext;
+
+    #[test]
+    fn context_expires_after_the_window() {
+        let ended = Instant::now();
+        let context = SessionContext::new("We shipped it.".to_string(), ended, Some(7));
+        let window = Duration::from_secs(60);
+
+        assert!(context.continues(ended + Duration::from_secs(5), Some(7), window));
+        assert!(context.continues(ended + window, Some(7), window));
+        assert!(!context.continues(ended + Duration::from_secs(61), Some(7), window));
+    }
+
+    #[test]
+    fn context_is_dropped_on_focus_change() {
+        let ended = Instant::now();
+        let context = SessionContext::new("We shipped it.".to_string(), ended, Some(7));
+        let later = ended + Duration::from_secs(1);
+        let window = Duration::from_secs(60);
+
+        assert!(!context.continues(later, Some(8), window));
+        assert!(!context.continues(later, None, window));
+
+        let unknown = SessionContext::new("We shipped it.".to_string(), ended, None);
+        assert!(unknown.continues(later, None, window));
+    }
+}
@@ -0,0 +1,253 @@
+use super::{transcriber::TimedWord, vocabulary::squash, DecodingProfile};
+
+/// Longest phrase, in words, checked for back-to-back repetition.
+const MAX_REPEATED_WORDS: usize = 8;
+
+/// Phrases whisper invents for silence, music or room noise, picked up from
+/// subtitle-heavy training data.
+pub fn default_blocklist() -> Vec<String> {
+    [
+        "Thank you.",
+        "Thanks for watching!",
+        "Thank you for watching.",
+        "Thank you so much for watching.",
+        "Please subscribe to my channel.",
+        "Subtitles by the Amara.org community",
+        "Transcription by CastingWords",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
+/// Whisper's own rule for a segment that is really silence: the model thinks
+/// there is no speech and is not confident in the text it produced anyway.
+pub fn is_silence(
+    no_speech_probability: f32,
+    mean_logprob: f32,
+    profile: &DecodingProfile,
+) -> bool {
+    no_speech_probability > profile.no_speech_threshold && mean_logprob < profile.logprob_threshold
+}
+
+/// Strips non-speech artifacts from decoded words before they reach the
+/// user's document.
+#[derive(Debug, Clone, Default)]
+pub struct HallucinationFilter {
+    blocklist: Vec<String>,
+}
+
+impl HallucinationFilter {
+    pub fn new(blocklist: &[String]) -> Self {
+        Self {
+            blocklist: blocklist
+                .iter()
+                .map(|phrase| squash(phrase))
+                .filter(|key| !key.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Drops sound annotations like `[BLANK_AUDIO]` or `(music)` anywhere,
+    /// collapses looping phrases to one copy, and drops blocklisted phrases
+    /// when they are all that was heard. `committed` is the session text
+    /// before this decode, so a "thank you" after it, or mid-sentence, stays.
+    pub fn filter(&self, committed: &[TimedWord], words: &[TimedWord]) -> Vec<TimedWord> {
+        let words = collapse_repetitions(drop_annotations(words));
+        let key = squash(
+            &committed
+                .iter()
+                .chain(&words)
+                .map(|word| word.text.as_str())
+                .collect::<String>(),
+        );
+        if self.blocklist.contains(&key) {
+            return Vec::new();
+        }
+        words
+    }
+}
+
+/// Whisper marks sounds rather than speech with `[...]`, `(...)`, `*...*` or
+/// `♪...♪`; drops every word inside such a span.
+fn drop_annotations(words: &[TimedWord]) -> Vec<TimedWord> {
+    let mut out = Vec::with_capacity(words.len());
+    let mut closer = None::<char>;
+    for word in words {
+        let text = word.text.trim_start_matches(['"', '\'', '-']);
+        if closer.is_none() {
+            closer = match text.chars().next() {
+                Some('[') => Some(']'),
+                Some('(') => Some(')'),
+                Some('*') => Some('*'),
+                Some(note @ ('♪' | '♫')) => Some(note),
+                _ => None,
+            };
+            if closer.is_some() {
+                // The opening mark itself must not count as the closer.
+                let rest = &text[text.chars().next().map_or(0, char::len_utf8)..];
+                if closes(rest, closer) {
+                    closer = None;
+                }
+                continue;
+            }
+        } else {
+            if closes(text, closer) {
+                closer = None;
+            }
+            continue;
+        }
+        out.push(word.clone());
+    }
+    out
+}
+
+fn closes(text: &str, closer: Option<char>) -> bool {
+    closer.is_some_and(|closer| {
+        text.trim_end_matches(['.', ',', '!', '?'])
+            .ends_with(closer)
+    })
+}
+
+/// Whisper can get stuck repeating a phrase on silence. A phrase said three
+/// times in a row (four for single words, which people do repeat) keeps its
+/// first occurrence only.
+fn collapse_repetitions(words: Vec<TimedWord>) -> Vec<TimedWord> {
+    let keys = words
+        .iter()
+        .map(|word| squash(&word.text))
+        .collect::<Vec<_>>();
+    let mut out = Vec::with_capacity(words.len());
+    let mut idx = 0usize;
+    'words: while idx < words.len() {
+        for len in 1..=MAX_REPEATED_WORDS {
+            let repeats = count_repeats(&keys, idx, len);
+            let limit = if len == 1 { 4 } else { 3 };
+            if repeats >= limit {
+                out.extend_from_slice(&words[idx..idx + len]);
+                idx += len * repeats;
+                continue 'words;
+            }
+        }
+        out.push(words[idx].clone());
+        idx += 1;
+    }
+    out
+}
+
+fn count_repeats(keys: &[String], start: usize, len: usize) -> usize {
+    let Some(phrase) = keys.get(start..start + len) else {
+        return 0;
+    };
+    if phrase.iter().any(|key| key.is_empty()) {
+        return 1;
+    }
+    let mut count = 1;
+    while keys.get(start + count * len..start + (count + 1) * len) == Some(phrase) {
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_blocklist, is_silence, HallucinationFilter};
+    use crate::core::{streaming::words_from_text, DecodingProfile};
+
+    /// Output captured from whisper models decoding silence, keyboard noise
+    /// and background music.
+    const HALLUCINATIONS: &[&str] = &[
+        "[BLANK_AUDIO]",
+        " [BLANK_AUDIO] [BLANK_AUDIO]",
+        "(music)",
+        " (upbeat music)",
+        "[Music]",
+        " *keyboard clicking*",
+        " ♪ ♪",
+        " ♪ Never gonna give you up ♪",
+        " Thank you.",
+        " Thank you!",
+        " Thanks for watching!",
+        " Thank you for watching.",
+        " Please subscribe to my channel.",
+        " Subtitles by the Amara.org community",
+        " (silence) Thank you.",
+        " Thank you. Thank you. Thank you. Thank you.",
+    ];
+
+    fn filter(text: &str) -> String {
+        HallucinationFilter::new(&default_blocklist())
+            .filter(&[], &words_from_text(text, 0, 1_000))
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn captured_hallucinations_are_dropped() {
+        for text in HALLUCINATIONS {
+            assert_eq!(filter(text), "", "{text:?} survived the filter");
+        }
+    }
+
+    #[test]
+    fn real_speech_survives() {
+        assert_eq!(
+            filter(" Thank you for the review, I'll merge it."),
+            "Thank you for the review, I'll merge it."
+        );
+        // A lone "you" is a common hallucination, but also a real answer.
+        assert_eq!(filter(" you"), "you");
+        assert_eq!(filter(" [SOUND] you"), "you");
+        assert_eq!(filter(" no no no"), "no no no");
+        assert_eq!(filter(" very very good"), "very very good");
+        assert_eq!(filter(" send it [BLANK_AUDIO] to Anya"), "send it to Anya");
+    }
+
+    #[test]
+    fn sign_off_after_committed_speech_survives() {
+        // A streamed or chunked session: earlier chunks are committed, and the
+        // last window only holds the closing words.
+        let filter = HallucinationFilter::new(&default_blocklist());
+        let committed = words_from_text(" I'll send the draft tomorrow.", 0, 3_000);
+        let sign_off = words_from_text(" Thank you.", 3_000, 3_800);
+        let kept = filter.filter(&committed, &sign_off);
+        assert_eq!(kept, sign_off);
+        assert!(filter.filter(&[], &sign_off).is_empty());
+    }
+
+    #[test]
+    fn looping_phrases_collapse_to_one_copy() {
+        assert_eq!(
+            filter(" and then we went to the store, the store, the store, the store,"),
+            "and then we went to the store,"
+        );
+        assert_eq!(filter(" okay okay okay okay okay"), "okay");
+    }
+
+    #[test]
+    fn blocklist_is_configurable() {
+        let filter = HallucinationFilter::new(&["Bye.".to_string()]);
+        assert!(filter
+            .filter(&[], &words_from_text(" bye", 0, 500))
+            .is_empty());
+        assert_eq!(
+            filter
+                .filter(&[], &words_from_text(" Thank you.", 0, 500))
+                .len(),
+            2
+        );
+        assert!(HallucinationFilter::default()
+            .filter(&[], &words_from_text(" [BLANK_AUDIO]", 0, 500))
+            .is_empty());
+    }
+
+    #[test]
+    fn silence_needs_no_speech_and_low_confidence() {
+        let profile = DecodingProfile::fast();
+        assert!(is_silence(0.9, -1.5, &profile));
+        assert!(!is_silence(0.9, -0.2, &profile));
+        assert!(!is_silence(0.1, -1.5, &profile));
+    }
+}
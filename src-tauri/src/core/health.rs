@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Parts of the pipeline that can fail independently of each other.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    Microphone,
+    WakeWord,
+    Transcriber,
+}
+
+impl Component {
+    pub const ALL: [Component; 3] = [
+        Component::Microphone,
+        Component::WakeWord,
+        Component::Transcriber,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Component::Microphone => "Microphone",
+            Component::WakeWord => "Wake word",
+            Component::Transcriber => "Transcription",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "state", content = "reason", rename_all = "snake_case")]
+pub enum Health {
+    Ok,
+    /// Working, but not as configured (fallback keyword, failing decodes).
+    Degraded(String),
+    /// Not working at all until the reason is fixed.
+    Disabled(String),
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ComponentHealth {
+    pub component: Component,
+    pub health: Health,
+}
+
+impl ComponentHealth {
+    /// One line for the tray menu, e.g. "Wake word: disabled (no access key)".
+    pub fn summary(&self) -> String {
+        let label = self.component.label();
+        match &self.health {
+            Health::Ok => format!("{label}: ok"),
+            Health::Degraded(reason) => format!("{label}: degraded ({reason})"),
+            Health::Disabled(reason) => format!("{label}: disabled ({reason})"),
+        }
+    }
+}
+
+/// Latest reported health of each component. Components that have not
+/// reported yet are still starting and are left out.
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    states: BTreeMap<Component, Health>,
+}
+
+impl HealthRegistry {
+    /// Records a report, returning whether it changed anything worth emitting.
+    pub fn update(&mut self, component: Component, health: Health) -> bool {
+        if self.states.get(&component) == Some(&health) {
+            return false;
+        }
+        self.states.insert(component, health);
+        true
+    }
+
+    pub fn snapshot(&self) -> Vec<ComponentHealth> {
+        self.states
+            .iter()
+            .map(|(component, health)| ComponentHealth {
+                component: *component,
+                health: health.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Component, ComponentHealth, Health, HealthRegistry};
+
+    #[test]
+    fn registry_reports_only_changes() {
+        let mut registry = HealthRegistry::default();
+        assert!(registry.update(Component::Transcriber, Health::Ok));
+        assert!(!registry.update(Component::Transcriber, Health::Ok));
+        assert!(registry.update(
+            Component::WakeWord,
+            Health::Disabled("no access key".to_string())
+        ));
+        assert!(registry.update(Component::Microphone, Health::Ok));
+
+        let snapshot = registry.snapshot();
+        assert_eq!(
+            snapshot
+                .iter()
+                .map(|entry| entry.component)
+                .collect::<Vec<_>>(),
+            vec![
+                Component::Microphone,
+                Component::WakeWord,
+                Component::Transcriber
+            ]
+        );
+        assert_eq!(snapshot[1].summary(), "Wake word: disabled (no access key)");
+    }
+
+    #[test]
+    fn health_serializes_with_reason() {
+        let entry = ComponentHealth {
+            component: Component::Transcriber,
+            health: Health::Degraded("server unreachable".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_string(&entry).unwrap(),
+            r#"{"component":"transcriber","health":{"state":"degraded","reason":"server unreachable"}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Health::Ok).unwrap(),
+            r#"{"state":"ok"}"#
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use super::{
+    streaming::words_from_text,
+    transcriber::{DecodeOptions, TimedWord, Transcription, TranscriptionBackend},
+    HttpBackendSettings,
+};
+
+const MULTIPART_BOUNDARY: &str = "lumitype-audio-boundary";
+
+/// Posts session audio to a whisper.cpp `server` (`/inference`) or an
+/// OpenAI-compatible `/v1/audio/transcriptions` endpoint on this machine.
+pub struct HttpTranscriber {
+    agent: ureq::Agent,
+    settings: HttpBackendSettings,
+}
+
+impl HttpTranscriber {
+    pub fn new(settings: HttpBackendSettings) -> Result<Self> {
+        validate_local_endpoint(&settings.endpoint)?;
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(settings.timeout_secs.max(1)))
+            .build();
+        Ok(Self { agent, settings })
+    }
+}
+
+impl TranscriptionBackend for HttpTranscriber {
+    fn transcribe(
+        &mut self,
+        samples_i16: &[i16],
+        options: &DecodeOptions,
+    ) -> Result<Transcription> {
+        if samples_i16.is_empty() {
+            return Ok(Transcription::default());
+        }
+
+        let wav = encode_wav_pcm16(samples_i16, 16_000);
+        let mut fields = vec![
+            ("response_format", "verbose_json"),
+            ("timestamp_granularities[]", "word"),
+        ];
+        if !self.settings.model.trim().is_empty() {
+            fields.push(("model", self.settings.model.trim()));
+        }
+        if let Some(language) = options.language.as_deref() {
+            fields.push(("language", language));
+        }
+        if options.translate {
+            fields.push(("translate", "true"));
+        }
+        if let Some(prompt) = options.prompt.as_deref() {
+            fields.push(("prompt", prompt));
+        }
+        // whisper.cpp's server reads these; OpenAI-style servers ignore them.
+        let decoding = &options.decoding;
+        let beam_size = decoding.beam_size.to_string();
+        let temperature_inc = decoding.temperature_increment.to_string();
+        let logprob_thold = decoding.logprob_threshold.to_string();
+        let no_speech_thold = decoding.no_speech_threshold.to_string();
+        let entropy_thold = decoding.entropy_threshold.to_string();
+        fields.extend([
+            ("temperature", "0"),
+            ("temperature_inc", temperature_inc.as_str()),
+            ("logprob_thold", logprob_thold.as_str()),
+            ("no_speech_thold", no_speech_thold.as_str()),
+            ("entropy_thold", entropy_thold.as_str()),
+        ]);
+        if decoding.beam_size > 1 {
+            fields.push(("beam_size", beam_size.as_str()));
+        }
+        let body = multipart_body(&fields, &wav);
+
+        let response = self
+            .agent
+            .post(&self.settings.endpoint)
+            .set(
+                "Content-Type",
+                &format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}"),
+            )
+            .send_bytes(&body)
+            .with_context(|| {
+                format!("transcription request to {} failed", self.settings.endpoint)
+            })?;
+
+        let content = response
+            .into_string()
+            .context("failed to read transcription response")?;
+        let duration_ms = (samples_i16.len() / 16) as u64;
+        parse_transcription_response(&content, duration_ms)
+    }
+}
+
+fn validate_local_endpoint(endpoint: &str) -> Result<()> {
+    let Some(rest) = endpoint.trim().strip_prefix("http://") else {
+        anyhow::bail!("transcription endpoint must be an http:// URL on this machine: {endpoint}");
+    };
+
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = if let Some(bracketed) = authority.strip_prefix('[') {
+        bracketed.split(']').next().unwrap_or_default()
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+
+    let is_loopback = host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false);
+    if !is_loopback {
+        anyhow::bail!("transcription endpoint must point at localhost, got host {host:?}");
+    }
+    Ok(())
+}
+
+fn encode_wav_pcm16(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        out.extend_from_slice(&sample.to_le_bytes());
+    }
+    out
+}
+
+fn multipart_body(fields: &[(&str, &str)], wav: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(wav.len() + 512);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"audio.wav\"\r\nContent-Type: audio/wav\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(wav);
+    body.extend_from_slice(format!("\r\n--{MULTIPART_BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+fn parse_transcription_response(content: &str, duration_ms: u64) -> Result<Transcription> {
+    let trimmed = content.trim();
+    if !trimmed.starts_with('{') {
+        return Ok(Transcription {
+            text: trimmed.to_string(),
+            language: None,
+            words: words_from_text(trimmed, 0, duration_ms),
+        });
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_str(trimmed).context("invalid JSON from transcription endpoint")?;
+    if let Some(error) = value.get("error") {
+        anyhow::bail!("transcription endpoint returned an error: {error}");
+    }
+    let text = value
+        .get("text")
+        .and_then(|text| text.as_str())
+        .map(str::to_string)
+        .context("transcription response is missing the \"text\" field")?;
+    let language = value
+        .get("language")
+        .and_then(|language| language.as_str())
+        .map(str::to_string);
+    let words = parse_words(&value).unwrap_or_else(|| words_from_text(&text, 0, duration_ms));
+    Ok(Transcription {
+        text,
+        language,
+        words,
+    })
+}
+
+/// Reads word timings from a `verbose_json` response: OpenAI puts them at the
+/// top level, whisper.cpp's server nests them in segments. Segments without
+/// words are spread evenly over their own time range. Word probabilities come
+/// from the word itself when reported, else from the segment's mean log
+/// probability.
+fn parse_words(value: &serde_json::Value) -> Option<Vec<TimedWord>> {
+    let seconds_to_ms = |value: Option<&serde_json::Value>| {
+        value
+            .and_then(|value| value.as_f64())
+            .map(|seconds| (seconds.max(0.0) * 1000.0).round() as u64)
+    };
+    let segment_probability = |segment: &serde_json::Value| {
+        segment
+            .get("avg_logprob")
+            .and_then(|value| value.as_f64())
+            .map(|logprob| logprob.exp().clamp(0.0, 1.0) as f32)
+            .unwrap_or(1.0)
+    };
+    let timed_words = |words: &[serde_json::Value], fallback: f32| {
+        words
+            .iter()
+            .filter_map(|word| {
+                let text = word.get("word")?.as_str()?.trim();
+                if text.is_empty() {
+                    return None;
+                }
+                let probability = word
+                    .get("probability")
+                    .and_then(|value| value.as_f64())
+                    .map(|probability| probability.clamp(0.0, 1.0) as f32)
+                    .unwrap_or(fallback);
+                Some(TimedWord {
+                    text: text.to_string(),
+                    start_ms: seconds_to_ms(word.get("start"))?,
+                    end_ms: seconds_to_ms(word.get("end"))?,
+                    probability,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if let Some(words) = value.get("words").and_then(|words| words.as_array()) {
+        return Some(timed_words(words, 1.0));
+    }
+
+    let segments = value.get("segments")?.as_array()?;
+    let mut words = Vec::new();
+    for segment in segments {
+        if let Some(segment_words) = segment.get("words").and_then(|words| words.as_array()) {
+            words.extend(timed_words(segment_words, segment_probability(segment)));
+            continue;
+        }
+        let text = segment.get("text").and_then(|text| text.as_str())?;
+        let start_ms = seconds_to_ms(segment.get("start"))?;
+        let end_ms = seconds_to_ms(segment.get("end"))?;
+        let probability = segment_probability(segment);
+        words.extend(
+            words_from_text(text, start_ms, end_ms)
+                .into_iter()
+                .map(|word| TimedWord {
+                    probability,
+                    ..word
+                }),
+        );
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::{
+        encode_wav_pcm16, parse_transcription_response, validate_local_endpoint, HttpTranscriber,
+    };
+    use crate::core::{
+        transcriber::{DecodeOptions, TranscriptionBackend},
+        HttpBackendSettings,
+    };
+
+    fn serve_once(response_body: &'static str) -> (String, thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/inference", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let header_end = loop {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..header_end]).to_ascii_lowercase();
+            let content_length = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            while request.len() < header_end + content_length {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            );
+            stream.write_all(reply.as_bytes()).unwrap();
+            request
+        });
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn posts_wav_and_returns_text_from_stub_server() {
+        let (endpoint, server) = serve_once(r#"{"text":" hallo vom Server","language":"de"}"#);
+        let mut backend = HttpTranscriber::new(HttpBackendSettings {
+            endpoint,
+            model: "whisper-1".to_string(),
+            timeout_secs: 5,
+        })
+        .unwrap();
+
+        let options = DecodeOptions {
+            language: Some("de".to_string()),
+            translate: false,
+            prompt: Some("Guten Morgen.".to_string()),
+            decoding: crate::core::DecodingProfile::accurate(),
+            final_pass: true,
+        };
+        let result = backend.transcribe(&[0i16; 1600], &options).unwrap();
+        assert_eq!(result.text, " hallo vom Server");
+        assert_eq!(result.language.as_deref(), Some("de"));
+
+        let request = server.join().unwrap();
+        let request = String::from_utf8_lossy(&request);
+        assert!(request.starts_with("POST /inference "));
+        assert!(request.contains("multipart/form-data; boundary="));
+        assert!(request.contains("name=\"file\"; filename=\"audio.wav\""));
+        assert!(request.contains("name=\"model\"\r\n\r\nwhisper-1"));
+        assert!(request.contains("name=\"language\"\r\n\r\nde"));
+        assert!(request.contains("name=\"prompt\"\r\n\r\nGuten Morgen."));
+        assert!(request.contains("name=\"beam_size\"\r\n\r\n5"));
+        assert!(request.contains("name=\"temperature_inc\"\r\n\r\n0.2"));
+        assert!(request.contains("RIFF"));
+    }
+
+    #[test]
+    fn rejects_non_local_endpoints() {
+        assert!(validate_local_endpoint("http://127.0.0.1:8080/inference").is_ok());
+        assert!(validate_local_endpoint("http://localhost:9000/v1/audio/transcriptions").is_ok());
+        assert!(validate_local_endpoint("http://[::1]:8080/inference").is_ok());
+        assert!(validate_local_endpoint("http://api.example.com/v1/audio/transcriptions").is_err());
+        assert!(validate_local_endpoint("https://localhost:8080/inference").is_err());
+    }
+
+    #[test]
+    fn wav_header_describes_mono_pcm16() {
+        let wav = encode_wav_pcm16(&[1, -1, 2], 16_000);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 6);
+        assert_eq!(wav.len(), 50);
+    }
+
+    #[test]
+    fn parses_json_plain_text_and_errors() {
+        assert_eq!(
+            parse_transcription_response(r#"{"text":"hi"}"#, 500)
+                .unwrap()
+                .text,
+            "hi"
+        );
+        assert_eq!(
+            parse_transcription_response("plain words\n", 1_000)
+                .unwrap()
+                .text,
+            "plain words"
+        );
+        assert!(parse_transcription_response(r#"{"error":"model not loaded"}"#, 0).is_err());
+    }
+
+    #[test]
+    fn parses_word_timings_from_verbose_json() {
+        let openai = parse_transcription_response(
+            r#"{"text":"hi there","words":[{"word":"hi","start":0.1,"end":0.3},{"word":"there","start":0.5,"end":0.9}]}"#,
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(openai.words.len(), 2);
+        assert_eq!(openai.words[1].text, "there");
+        assert_eq!((openai.words[1].start_ms, openai.words[1].end_ms), (500, 900));
+        assert_eq!(openai.words[1].probability, 1.0);
+
+        let server = parse_transcription_response(
+            r#"{"text":" one two","segments":[{"text":" one two","start":1.0,"end":2.0,"avg_logprob":-0.5}]}"#,
+            3_000,
+        )
+        .unwrap();
+        assert_eq!(server.words[0].start_ms, 1_000);
+        assert_eq!(server.words[1].end_ms, 2_000);
+        assert!((server.words[0].probability - (-0.5f32).exp()).abs() < 1e-6);
+
+        let scored = parse_transcription_response(
+            r#"{"text":"hi","segments":[{"avg_logprob":-0.1,"words":[{"word":"hi","start":0.0,"end":0.2,"probability":0.3}]}]}"#,
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(scored.words[0].probability, 0.3);
+    }
+}
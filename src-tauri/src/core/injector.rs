@@ -1,38 +1,118 @@
 use enigo::{Direction, Enigo, Key, Keyboard, Settings};
 use tokio::sync::mpsc;
 
+use super::{
+    command_mode::KeyChord,
+    state::TranscriptEdit,
+    voice_commands::{without_last_phrase, without_last_word},
+};
+
 #[derive(Debug)]
 pub enum InjectionMessage {
     BeginSession,
-    Delta(String),
+    Edit(TranscriptEdit),
     CommitSession,
     CancelSession,
     UndoLast,
+    /// Deletes the last phrase of the previous session, keeping what the
+    /// current one typed after it.
+    DeletePreviousPhrase,
+    DeletePreviousWord,
+    /// Presses each chord in turn (command mode).
+    PressKeys(Vec<KeyChord>),
+}
+
+/// Keyboard output used by the injector; a text buffer stands in for it in tests.
+pub(crate) trait KeySink {
+    fn type_char(&mut self, ch: char) -> Result<(), ()>;
+    fn backspace(&mut self) -> Result<(), ()>;
+}
+
+impl KeySink for Enigo {
+    fn type_char(&mut self, ch: char) -> Result<(), ()> {
+        // Typed as a key press; some apps ignore a newline sent as text.
+        if ch == '\n' {
+            return self.key(Key::Return, Direction::Click).map_err(|_| ());
+        }
+        self.text(&ch.to_string()).map_err(|_| ())
+    }
+
+    fn backspace(&mut self) -> Result<(), ()> {
+        self.key(Key::Backspace, Direction::Click).map_err(|_| ())
+    }
+}
+
+/// What the current session should read (`target`) versus what has actually
+/// been typed into the focused field so far (`typed`).
+#[derive(Debug, Default)]
+struct SessionText {
+    target: String,
+    typed: String,
+}
+
+impl SessionText {
+    fn clear(&mut self) {
+        self.target.clear();
+        self.typed.clear();
+    }
+
+    /// Shortens the text typed before this session to `kept` bytes of
+    /// `previous`: erases this session's text, the rest of `previous`, then
+    /// retypes this session's text.
+    fn trim_previous(
+        &mut self,
+        sink: &mut dyn KeySink,
+        previous: &mut String,
+        kept: usize,
+    ) -> Result<(), ()> {
+        let removed = previous[kept..].chars().count();
+        while !self.typed.is_empty() {
+            sink.backspace()?;
+            self.typed.pop();
+        }
+        for _ in 0..removed {
+            sink.backspace()?;
+            previous.pop();
+        }
+        self.sync(sink)
+    }
+
+    /// Backspaces and types until the field matches the target, stopping at
+    /// the first keyboard failure so `typed` stays accurate.
+    fn sync(&mut self, sink: &mut dyn KeySink) -> Result<(), ()> {
+        let edit = TranscriptEdit::between(&self.typed, &self.target);
+        for _ in 0..edit.retract {
+            sink.backspace()?;
+            self.typed.pop();
+        }
+        for ch in edit.insert.chars() {
+            sink.type_char(ch)?;
+            self.typed.push(ch);
+        }
+        Ok(())
+    }
 }
 
 pub fn spawn_injection_worker(mut rx: mpsc::Receiver<InjectionMessage>) {
     std::thread::spawn(move || {
         let mut enigo = Enigo::new(&Settings::default()).ok();
-        let mut active_session = String::new();
+        let mut session = SessionText::default();
         let mut last_session = String::new();
 
         while let Some(message) = rx.blocking_recv() {
             match message {
                 InjectionMessage::BeginSession => {
-                    active_session.clear();
+                    session.clear();
                 }
-                InjectionMessage::Delta(delta) => {
-                    if delta.is_empty() || secure_input_enabled() {
+                InjectionMessage::Edit(edit) => {
+                    edit.apply(&mut session.target);
+                    if secure_input_enabled() {
                         continue;
                     }
                     let mut reset_enigo = false;
                     if let Some(writer) = ensure_enigo(&mut enigo) {
-                        for ch in delta.chars() {
-                            if writer.text(&ch.to_string()).is_err() {
-                                reset_enigo = true;
-                                break;
-                            }
-                            active_session.push(ch);
+                        if session.sync(writer).is_err() {
+                            reset_enigo = true;
                         }
                     }
                     if reset_enigo {
@@ -40,20 +120,20 @@ pub fn spawn_injection_worker(mut rx: mpsc::Receiver<InjectionMessage>) {
                     }
                 }
                 InjectionMessage::CommitSession => {
-                    last_session = active_session.clone();
-                    active_session.clear();
+                    last_session = session.typed.clone();
+                    session.clear();
                 }
                 InjectionMessage::CancelSession => {
                     let mut reset_enigo = false;
                     if let Some(writer) = ensure_enigo(&mut enigo) {
-                        if backspace_text(writer, active_session.chars().count()).is_err() {
+                        if backspace_text(writer, session.typed.chars().count()).is_err() {
                             reset_enigo = true;
                         }
                     }
                     if reset_enigo {
                         enigo = None;
                     }
-                    active_session.clear();
+                    session.clear();
                 }
                 InjectionMessage::UndoLast => {
                     if last_session.is_empty() {
@@ -70,11 +150,66 @@ pub fn spawn_injection_worker(mut rx: mpsc::Receiver<InjectionMessage>) {
                     }
                     last_session.clear();
                 }
+                InjectionMessage::PressKeys(chords) => {
+                    if secure_input_enabled() {
+                        continue;
+                    }
+                    let mut reset_enigo = false;
+                    if let Some(writer) = ensure_enigo(&mut enigo) {
+                        reset_enigo = chords
+                            .iter()
+                            .any(|chord| press_chord(writer, chord).is_err());
+                    }
+                    if reset_enigo {
+                        enigo = None;
+                    }
+                    // The keys may have moved the cursor away from the
+                    // previous dictation, so it can no longer be undone.
+                    last_session.clear();
+                }
+                InjectionMessage::DeletePreviousPhrase | InjectionMessage::DeletePreviousWord => {
+                    let kept = match message {
+                        InjectionMessage::DeletePreviousPhrase => {
+                            without_last_phrase(&last_session).len()
+                        }
+                        _ => without_last_word(&last_session).trim_end().len(),
+                    };
+                    let mut reset_enigo = false;
+                    if let Some(writer) = ensure_enigo(&mut enigo) {
+                        if session.trim_previous(writer, &mut last_session, kept).is_err() {
+                            reset_enigo = true;
+                        }
+                    }
+                    if reset_enigo {
+                        enigo = None;
+                    }
+                }
             }
         }
     });
 }
 
+/// Holds the chord's modifiers around a click of its key; modifiers are
+/// released even when the click fails so none stays stuck down.
+fn press_chord(enigo: &mut Enigo, chord: &KeyChord) -> Result<(), ()> {
+    let mut held = Vec::new();
+    let mut result = Ok(());
+    for modifier in &chord.modifiers {
+        if enigo.key(*modifier, Direction::Press).is_err() {
+            result = Err(());
+            break;
+        }
+        held.push(*modifier);
+    }
+    if result.is_ok() {
+        result = enigo.key(chord.key, Direction::Click).map_err(|_| ());
+    }
+    for modifier in held.into_iter().rev() {
+        let _ = enigo.key(modifier, Direction::Release);
+    }
+    result
+}
+
 fn ensure_enigo(enigo: &mut Option<Enigo>) -> Option<&mut Enigo> {
     if enigo.is_none() {
         *enigo = Enigo::new(&Settings::default()).ok();
@@ -82,9 +217,9 @@ fn ensure_enigo(enigo: &mut Option<Enigo>) -> Option<&mut Enigo> {
     enigo.as_mut()
 }
 
-fn backspace_text(enigo: &mut Enigo, count: usize) -> Result<(), ()> {
+fn backspace_text(sink: &mut dyn KeySink, count: usize) -> Result<(), ()> {
     for _ in 0..count {
-        enigo.key(Key::Backspace, Direction::Click).map_err(|_| ())?;
+        sink.backspace()?;
     }
     Ok(())
 }
@@ -103,3 +238,132 @@ fn secure_input_enabled() -> bool {
 fn secure_input_enabled() -> bool {
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{KeySink, SessionText};
+    use crate::core::{
+        state::TranscriptEdit,
+        voice_commands::{without_last_phrase, without_last_word},
+    };
+
+    /// A text field that fails every `fail_every`-th keystroke when set.
+    #[derive(Default)]
+    struct FakeField {
+        text: String,
+        keystrokes: usize,
+        fail_every: Option<usize>,
+    }
+
+    impl FakeField {
+        fn keystroke(&mut self) -> Result<(), ()> {
+            self.keystrokes += 1;
+            match self.fail_every {
+                Some(every) if self.keystrokes % every == 0 => Err(()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl KeySink for FakeField {
+        fn type_char(&mut self, ch: char) -> Result<(), ()> {
+            self.keystroke()?;
+            self.text.push(ch);
+            Ok(())
+        }
+
+        fn backspace(&mut self) -> Result<(), ()> {
+            self.keystroke()?;
+            self.text.pop();
+            Ok(())
+        }
+    }
+
+    fn hypotheses() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::vec("[a-cé ,.]{0,24}", 1..12)
+    }
+
+    #[test]
+    fn revised_word_is_corrected_in_place() {
+        let mut field = FakeField::default();
+        let mut session = SessionText::default();
+        let mut emitted = String::new();
+
+        for hypothesis in ["Their", "Their cat", "There cat sat"] {
+            let edit = TranscriptEdit::between(&emitted, hypothesis);
+            edit.apply(&mut session.target);
+            session.sync(&mut field).unwrap();
+            emitted = hypothesis.to_string();
+        }
+        assert_eq!(field.text, "There cat sat");
+    }
+
+    #[test]
+    fn previous_phrase_is_deleted_before_the_current_session() {
+        let mut previous = "First one. Second one.".to_string();
+        let mut field = FakeField {
+            text: previous.clone(),
+            ..FakeField::default()
+        };
+        let mut session = SessionText::default();
+        TranscriptEdit::between("", " Third").apply(&mut session.target);
+        session.sync(&mut field).unwrap();
+
+        let kept = without_last_phrase(&previous).len();
+        session
+            .trim_previous(&mut field, &mut previous, kept)
+            .unwrap();
+        assert_eq!(field.text, "First one. Third");
+        assert_eq!(previous, "First one.");
+
+        let kept = without_last_word(&previous).trim_end().len();
+        session
+            .trim_previous(&mut field, &mut previous, kept)
+            .unwrap();
+        assert_eq!(field.text, "First Third");
+    }
+
+    proptest! {
+        #[test]
+        fn typed_text_matches_latest_hypothesis(hypotheses in hypotheses()) {
+            let mut field = FakeField::default();
+            let mut session = SessionText::default();
+            let mut emitted = String::new();
+
+            for hypothesis in &hypotheses {
+                let edit = TranscriptEdit::between(&emitted, hypothesis);
+                edit.apply(&mut session.target);
+                session.sync(&mut field).unwrap();
+                emitted = hypothesis.clone();
+                prop_assert_eq!(&field.text, hypothesis);
+            }
+        }
+
+        #[test]
+        fn keyboard_failures_recover_on_next_edit(
+            hypotheses in hypotheses(),
+            fail_every in 3usize..20,
+        ) {
+            let mut field = FakeField {
+                fail_every: Some(fail_every),
+                ..FakeField::default()
+            };
+            let mut session = SessionText::default();
+            let mut emitted = String::new();
+
+            for hypothesis in &hypotheses {
+                let edit = TranscriptEdit::between(&emitted, hypothesis);
+                edit.apply(&mut session.target);
+                let _ = session.sync(&mut field);
+                emitted = hypothesis.clone();
+                prop_assert_eq!(&field.text, &session.typed);
+            }
+
+            field.fail_every = None;
+            session.sync(&mut field).unwrap();
+            prop_assert_eq!(&field.text, hypotheses.last().unwrap());
+        }
+    }
+}
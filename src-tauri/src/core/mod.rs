@@ -1,9 +1,22 @@
 pub mod audio;
+pub mod batch;
+pub mod cadence;
+pub mod command_mode;
+pub mod context;
+pub mod filter;
+pub mod health;
+pub mod http_backend;
 pub mod injector;
+pub mod models;
 pub mod permissions;
+pub mod postprocess;
+pub mod snippets;
 pub mod state;
+pub mod streaming;
 pub mod transcriber;
 pub mod vad;
+pub mod vocabulary;
+pub mod voice_commands;
 pub mod wake_word;
 
 use std::{path::PathBuf, sync::Arc};
@@ -14,10 +27,14 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::{broadcast, mpsc};
 
 use self::{
+    cadence::DecodeStats,
+    health::{Component, ComponentHealth, Health, HealthRegistry},
     injector::InjectionMessage,
-    state::{DictationState, EngineCommand, EngineEvent, StateMachine},
-    transcriber::TranscriberMessage,
+    snippets::Snippet,
+    state::{DictationMode, DictationState, EngineCommand, EngineEvent, StateMachine},
+    transcriber::{TranscriberConfig, TranscriberMessage},
     vad::VadMessage,
+    voice_commands::VoiceCommand,
     wake_word::WakeWordConfig,
 };
 
@@ -28,30 +45,201 @@ pub struct AudioFrame {
     pub peak: f32,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// Identifies a `ggml-<id>.bin` file in the model catalog, e.g. `base.en` or
+/// `large-v3-turbo-q5_0`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub struct TranscriptionModel(String);
+
+impl TranscriptionModel {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn base_en() -> Self {
+        Self::new("base.en")
+    }
+
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("ggml-{}.bin", self.0)
+    }
+}
+
+impl From<String> for TranscriptionModel {
+    fn from(value: String) -> Self {
+        // Settings written before the catalog existed stored enum variants.
+        match value.as_str() {
+            "base_en" => Self::new("base.en"),
+            "tiny_en" => Self::new("tiny.en"),
+            _ => Self(value),
+        }
+    }
+}
+
+impl From<TranscriptionModel> for String {
+    fn from(value: TranscriptionModel) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
-pub enum TranscriptionModel {
-    BaseEn,
-    TinyEn,
+pub enum TranscriptionBackendKind {
+    #[default]
+    Local,
+    Http,
 }
 
-impl TranscriptionModel {
-    pub fn file_name(self) -> &'static str {
-        match self {
-            TranscriptionModel::BaseEn => "ggml-base.en.bin",
-            TranscriptionModel::TinyEn => "ggml-tiny.en.bin",
+/// Connection settings for a whisper.cpp `server` or OpenAI-compatible
+/// transcription service running on this machine.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpBackendSettings {
+    pub endpoint: String,
+    pub model: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for HttpBackendSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://127.0.0.1:8080/inference".to_string(),
+            model: String::new(),
+            timeout_secs: 15,
         }
     }
 }
 
+/// Whisper search and fallback settings for one kind of decode.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DecodingProfile {
+    /// 1 decodes greedily; larger values run beam search with this many beams.
+    pub beam_size: u32,
+    /// Temperature added on each retry when a decode fails the thresholds;
+    /// 0 disables fallback.
+    pub temperature_increment: f32,
+    /// Retry when the average token log probability falls below this.
+    pub logprob_threshold: f32,
+    /// Treat a segment as silence above this no-speech probability.
+    pub no_speech_threshold: f32,
+    /// Retry when token entropy rises above this (repetitive output).
+    pub entropy_threshold: f32,
+}
+
+impl DecodingProfile {
+    /// Greedy and single-pass, for the partials typed while speaking.
+    pub fn fast() -> Self {
+        Self {
+            beam_size: 1,
+            temperature_increment: 0.0,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            entropy_threshold: 2.4,
+        }
+    }
+
+    /// Beam search with temperature fallback, for the final decode.
+    pub fn accurate() -> Self {
+        Self {
+            beam_size: 5,
+            temperature_increment: 0.2,
+            ..Self::fast()
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=8).contains(&self.beam_size) {
+            anyhow::bail!("beam size must be between 1 and 8, got {}", self.beam_size);
+        }
+        if !(0.0..=1.0).contains(&self.temperature_increment) {
+            anyhow::bail!(
+                "temperature increment must be between 0 and 1, got {}",
+                self.temperature_increment
+            );
+        }
+        if !(0.0..=1.0).contains(&self.no_speech_threshold) {
+            anyhow::bail!(
+                "no-speech threshold must be between 0 and 1, got {}",
+                self.no_speech_threshold
+            );
+        }
+        if self.logprob_threshold > 0.0 || self.entropy_threshold <= 0.0 {
+            anyhow::bail!("logprob threshold must be <= 0 and entropy threshold > 0");
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodingProfile {
+    fn default() -> Self {
+        Self::fast()
+    }
+}
+
+/// When the bundled whisper model is held in memory.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelLoadPolicy {
+    /// Load at startup and keep it resident.
+    #[default]
+    Always,
+    /// Load when the first dictation starts, then keep it.
+    OnFirstDictation,
+    /// Load when a dictation starts and free it after a stretch without one.
+    UnloadWhenIdle,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EngineSettings {
     pub enabled: bool,
     pub launch_at_startup: bool,
     pub microphone: String,
     pub sensitivity: f32,
     pub model: TranscriptionModel,
+    /// Faster model for the live partials (cascade mode); `model` still
+    /// decodes the final text. `None` uses `model` for both.
+    pub partial_model: Option<TranscriptionModel>,
     pub push_to_talk_hotkey: String,
+    /// Starts a session that types the English translation of any spoken language.
+    pub translate_hotkey: String,
+    /// Starts a session whose utterance is run as a keyboard command.
+    pub command_hotkey: String,
+    /// Mode used for sessions started by the wake word or push-to-talk.
+    pub default_mode: DictationMode,
+    pub backend: TranscriptionBackendKind,
+    pub http_backend: HttpBackendSettings,
+    /// Whisper language code, or `"auto"` to detect it at the start of each session.
+    pub language: String,
+    /// Names and jargon whisper should spell correctly; passed as prompt context.
+    pub vocabulary: Vec<String>,
+    pub correct_vocabulary: bool,
+    /// Phrases whisper invents on silence; dropped when they are all it heard.
+    pub hallucination_blocklist: Vec<String>,
+    /// Decoding used for partial hypotheses while the user is speaking.
+    pub partial_decoding: DecodingProfile,
+    /// Decoding used for the final pass when the session ends.
+    pub final_decoding: DecodingProfile,
+    /// Inference threads for the bundled model; 0 picks one from the core count.
+    pub decode_threads: u32,
+    pub model_load_policy: ModelLoadPolicy,
+    /// Idle minutes before the model is unloaded under `UnloadWhenIdle`.
+    pub model_idle_minutes: u32,
+    /// Pass the end of the previous dictation as context to the next one
+    /// when it starts in the same app within `context_window_seconds`.
+    pub carry_context: bool,
+    pub context_window_seconds: u32,
+    /// Turn spoken "comma", "new line" and the like into symbols and breaks.
+    pub spoken_punctuation: bool,
+    /// Act on "scratch that", "delete last word", "undo that" and "cancel that".
+    pub voice_commands: bool,
+    /// Spoken phrases typed as longer text, such as a signature or address.
+    pub snippets: Vec<Snippet>,
 }
 
 impl Default for EngineSettings {
@@ -61,8 +249,28 @@ impl Default for EngineSettings {
             launch_at_startup: false,
             microphone: String::new(),
             sensitivity: 0.45,
-            model: TranscriptionModel::BaseEn,
+            model: TranscriptionModel::base_en(),
+            partial_model: None,
             push_to_talk_hotkey: "Cmd+Shift+Space".to_string(),
+            translate_hotkey: "Cmd+Alt+Space".to_string(),
+            command_hotkey: "Cmd+Alt+K".to_string(),
+            default_mode: DictationMode::Transcribe,
+            backend: TranscriptionBackendKind::Local,
+            http_backend: HttpBackendSettings::default(),
+            language: "en".to_string(),
+            vocabulary: Vec::new(),
+            correct_vocabulary: false,
+            hallucination_blocklist: filter::default_blocklist(),
+            partial_decoding: DecodingProfile::fast(),
+            final_decoding: DecodingProfile::accurate(),
+            decode_threads: 0,
+            model_load_policy: ModelLoadPolicy::Always,
+            model_idle_minutes: 10,
+            carry_context: false,
+            context_window_seconds: 60,
+            spoken_punctuation: true,
+            voice_commands: true,
+            snippets: Vec::new(),
         }
     }
 }
@@ -72,6 +280,8 @@ pub struct EngineHandle {
     command_tx: mpsc::Sender<EngineCommand>,
     events_tx: broadcast::Sender<EngineEvent>,
     settings: Arc<RwLock<EngineSettings>>,
+    health: Arc<RwLock<HealthRegistry>>,
+    decode_stats: Arc<RwLock<DecodeStats>>,
 }
 
 impl EngineHandle {
@@ -87,6 +297,16 @@ impl EngineHandle {
         self.settings.read().clone()
     }
 
+    /// Latest health of every component that has reported so far.
+    pub fn health(&self) -> Vec<ComponentHealth> {
+        self.health.read().snapshot()
+    }
+
+    /// Decode speed and cadence as of the last dictation session.
+    pub fn decode_stats(&self) -> DecodeStats {
+        self.decode_stats.read().clone()
+    }
+
     pub async fn apply_settings(&self, next: EngineSettings) {
         {
             *self.settings.write() = next.clone();
@@ -106,13 +326,85 @@ impl EngineHandle {
             .await;
         let _ = self
             .command_tx
-            .send(EngineCommand::UpdateModel(next.model))
+            .send(EngineCommand::UpdateModel(
+                next.model.clone(),
+                next.partial_model.clone(),
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateBackend(
+                next.backend,
+                next.http_backend.clone(),
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateLanguage(next.language.clone()))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateDefaultMode(next.default_mode))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateVocabulary(
+                next.vocabulary.clone(),
+                next.correct_vocabulary,
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateBlocklist(
+                next.hallucination_blocklist.clone(),
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateDecoding(
+                next.partial_decoding,
+                next.final_decoding,
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateThreads(next.decode_threads))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateLoadPolicy(
+                next.model_load_policy,
+                next.model_idle_minutes,
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateContextCarry(
+                next.carry_context,
+                next.context_window_seconds,
+            ))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateSpokenPunctuation(next.spoken_punctuation))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateVoiceCommands(next.voice_commands))
+            .await;
+        let _ = self
+            .command_tx
+            .send(EngineCommand::UpdateSnippets(next.snippets))
             .await;
     }
 }
 
 pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Result<EngineHandle> {
     let settings = Arc::new(RwLock::new(initial_settings.clone()));
+    let health = Arc::new(RwLock::new(HealthRegistry::default()));
+    let health_registry = health.clone();
+    let decode_stats = Arc::new(RwLock::new(DecodeStats::default()));
+    let latest_decode_stats = decode_stats.clone();
 
     let (command_tx, mut command_rx) = mpsc::channel::<EngineCommand>(1024);
     let (events_tx, _) = broadcast::channel::<EngineEvent>(1024);
@@ -129,8 +421,7 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
     transcriber::spawn_transcriber_worker(
         transcriber_rx,
         command_tx.clone(),
-        model_root,
-        initial_settings.model,
+        TranscriberConfig::from_settings(&initial_settings, model_root),
     );
     injector::spawn_injection_worker(injector_rx);
 
@@ -144,6 +435,7 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
             &events_tx_for_loop,
         );
 
+        let mut default_mode = initial_settings.default_mode;
         let mut machine = StateMachine::new(initial_settings.enabled);
         emit_state_events(&events_tx_for_loop, &machine);
 
@@ -160,13 +452,22 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
                         let _ = events_tx_for_loop.send(EngineEvent::OverlayWave(frame.peak));
                     }
                 }
-                EngineCommand::WakeDetected | EngineCommand::PushToTalkTriggered => {
+                EngineCommand::WakeDetected
+                | EngineCommand::PushToTalkTriggered
+                | EngineCommand::TranslateTriggered
+                | EngineCommand::CommandTriggered => {
+                    let mode = match command {
+                        EngineCommand::TranslateTriggered => DictationMode::Translate,
+                        EngineCommand::CommandTriggered => DictationMode::Command,
+                        _ => default_mode,
+                    };
                     if machine.try_start_dictation() {
-                        let _ = transcriber_tx.blocking_send(TranscriberMessage::Begin);
+                        let _ = transcriber_tx.blocking_send(TranscriberMessage::Begin(mode));
                         let _ = vad_tx.blocking_send(VadMessage::Begin);
                         let _ = injector_tx.blocking_send(InjectionMessage::BeginSession);
 
                         let _ = events_tx_for_loop.send(EngineEvent::OverlayReset);
+                        let _ = events_tx_for_loop.send(EngineEvent::DictationModeChanged(mode));
                         let _ = events_tx_for_loop.send(EngineEvent::OverlayVisibility(true));
                         emit_state_events(&events_tx_for_loop, &machine);
                     }
@@ -178,16 +479,41 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
                         emit_state_events(&events_tx_for_loop, &machine);
                     }
                 }
-                EngineCommand::TranscriptionDelta(delta) => {
+                EngineCommand::TranscriptionEdit(edit) => {
                     if matches!(
                         machine.state(),
                         DictationState::Dictating | DictationState::Stopping
                     ) {
-                        let _ =
-                            events_tx_for_loop.send(EngineEvent::OverlayTextDelta(delta.clone()));
-                        let _ = injector_tx.blocking_send(InjectionMessage::Delta(delta));
+                        let _ = events_tx_for_loop.send(EngineEvent::OverlayTextEdit(edit.clone()));
+                        let _ = injector_tx.blocking_send(InjectionMessage::Edit(edit));
+                    }
+                }
+                EngineCommand::TranscriptFinalized(transcript) => {
+                    if machine.state() == DictationState::Stopping {
+                        let _ = events_tx_for_loop
+                            .send(EngineEvent::TranscriptFinalized(transcript));
+                    }
+                }
+                EngineCommand::ModelLoadChanged(status) => {
+                    let _ = events_tx_for_loop.send(EngineEvent::ModelLoadChanged(status));
+                }
+                EngineCommand::LanguageDetected(language) => {
+                    let _ = events_tx_for_loop.send(EngineEvent::LanguageDetected(language));
+                }
+                EngineCommand::ReportHealth(component, health) => {
+                    if health_registry.write().update(component, health.clone()) {
+                        let _ = events_tx_for_loop.send(EngineEvent::HealthChanged(
+                            ComponentHealth { component, health },
+                        ));
                     }
                 }
+                EngineCommand::ReportDecodeStats(stats) => {
+                    *latest_decode_stats.write() = stats.clone();
+                    let _ = events_tx_for_loop.send(EngineEvent::DecodeStatsChanged(stats));
+                }
+                EngineCommand::ReportError(message) => {
+                    let _ = events_tx_for_loop.send(EngineEvent::Error(message));
+                }
                 EngineCommand::TranscriptionFinished => {
                     let _ = injector_tx.blocking_send(InjectionMessage::CommitSession);
                     if machine.finish_stopping() {
@@ -206,8 +532,24 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
                         emit_state_events(&events_tx_for_loop, &machine);
                     }
                 }
+                EngineCommand::PressKeys(chords) => {
+                    let _ = injector_tx.blocking_send(InjectionMessage::PressKeys(chords));
+                }
+                EngineCommand::VoiceCommand(command) => {
+                    let message = match command {
+                        VoiceCommand::ScratchThat => InjectionMessage::DeletePreviousPhrase,
+                        VoiceCommand::DeleteLastWord => InjectionMessage::DeletePreviousWord,
+                        VoiceCommand::Undo => InjectionMessage::UndoLast,
+                        VoiceCommand::Cancel => InjectionMessage::CancelSession,
+                    };
+                    let _ = injector_tx.blocking_send(message);
+                    if command == VoiceCommand::Undo {
+                        let _ = transcriber_tx.blocking_send(TranscriberMessage::ClearContext);
+                    }
+                }
                 EngineCommand::UndoLastDictation => {
                     let _ = injector_tx.blocking_send(InjectionMessage::UndoLast);
+                    let _ = transcriber_tx.blocking_send(TranscriberMessage::ClearContext);
                 }
                 EngineCommand::SetEnabled(enabled) => {
                     if machine.set_enabled(enabled) {
@@ -231,9 +573,62 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
                 }
                 EngineCommand::UpdateSensitivity(value) => {
                     let _ = vad_tx.blocking_send(VadMessage::SetSensitivity(value));
+                    let _ =
+                        transcriber_tx.blocking_send(TranscriberMessage::UpdateSensitivity(value));
+                }
+                EngineCommand::UpdateModel(model, partial) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateModel(model, partial));
                 }
-                EngineCommand::UpdateModel(model) => {
-                    let _ = transcriber_tx.blocking_send(TranscriberMessage::UpdateModel(model));
+                EngineCommand::UpdateBackend(kind, http) => {
+                    let _ =
+                        transcriber_tx.blocking_send(TranscriberMessage::UpdateBackend(kind, http));
+                }
+                EngineCommand::UpdateLanguage(language) => {
+                    let _ =
+                        transcriber_tx.blocking_send(TranscriberMessage::UpdateLanguage(language));
+                }
+                EngineCommand::UpdateDefaultMode(mode) => {
+                    default_mode = mode;
+                }
+                EngineCommand::UpdateVocabulary(terms, correct) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateVocabulary(terms, correct));
+                }
+                EngineCommand::UpdateBlocklist(blocklist) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateBlocklist(blocklist));
+                }
+                EngineCommand::UpdateDecoding(partial, final_pass) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateDecoding(partial, final_pass));
+                }
+                EngineCommand::UpdateThreads(threads) => {
+                    let _ = transcriber_tx.blocking_send(TranscriberMessage::UpdateThreads(threads));
+                }
+                EngineCommand::UpdateLoadPolicy(policy, minutes) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateLoadPolicy(policy, minutes));
+                }
+                EngineCommand::UpdateContextCarry(enabled, seconds) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateContextCarry(enabled, seconds));
+                }
+                EngineCommand::UpdateSpokenPunctuation(enabled) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateSpokenPunctuation(enabled));
+                }
+                EngineCommand::UpdateVoiceCommands(enabled) => {
+                    let _ = transcriber_tx
+                        .blocking_send(TranscriberMessage::UpdateVoiceCommands(enabled));
+                }
+                EngineCommand::UpdateSnippets(snippets) => {
+                    let _ =
+                        transcriber_tx.blocking_send(TranscriberMessage::UpdateSnippets(snippets));
+                }
+                EngineCommand::UpdateCommands(commands) => {
+                    let _ =
+                        transcriber_tx.blocking_send(TranscriberMessage::UpdateCommands(commands));
                 }
                 EngineCommand::PermissionsChecked(status) => {
                     if status.microphone && audio_capture.is_none() {
@@ -255,6 +650,8 @@ pub fn spawn_engine(initial_settings: EngineSettings, model_root: PathBuf) -> Re
         command_tx,
         events_tx,
         settings,
+        health,
+        decode_stats,
     })
 }
 
@@ -274,9 +671,19 @@ fn try_start_audio_capture(
         Some(preferred_microphone.to_string())
     };
 
+    // Called from the engine loop itself, so the report must not block on
+    // the loop's own queue.
     match audio::AudioCapture::start(command_tx.clone(), preferred) {
-        Ok(capture) => Some(capture),
-        Err(_) => {
+        Ok(capture) => {
+            let _ =
+                command_tx.try_send(EngineCommand::ReportHealth(Component::Microphone, Health::Ok));
+            Some(capture)
+        }
+        Err(error) => {
+            let _ = command_tx.try_send(EngineCommand::ReportHealth(
+                Component::Microphone,
+                Health::Disabled(error.to_string()),
+            ));
             let _ = events_tx.send(EngineEvent::Error(
                 "Unable to start microphone stream; check microphone permission and selected device."
                     .to_string(),
@@ -0,0 +1,770 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::TranscriptionModel;
+
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGML_QNT_VERSION_FACTOR: i32 = 1000;
+const ENGLISH_ONLY_VOCAB: i32 = 51_864;
+
+/// Checksums of known models, kept in the model root next to them.
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Suffix of a model file that is still being copied in.
+const PARTIAL_SUFFIX: &str = ".partial";
+const COPY_CHUNK_BYTES: usize = 1 << 20;
+
+/// One `ggml-*.bin` whisper model found in the model root.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelInfo {
+    pub id: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub model_type: String,
+    pub multilingual: bool,
+    pub quantization: String,
+    pub status: ModelStatus,
+    /// Why the file cannot be trusted or loaded, for display next to it.
+    pub error: Option<String>,
+}
+
+impl ModelInfo {
+    /// Whether whisper can be pointed at this file.
+    pub fn usable(&self) -> bool {
+        matches!(self.status, ModelStatus::Verified | ModelStatus::Unverified)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelStatus {
+    /// Hashed clean against a known checksum and unchanged since.
+    Verified,
+    /// Looks like a whisper model but has not been checked against a checksum.
+    Unverified,
+    /// Not a whisper model, or its contents differ from the manifest.
+    Corrupt,
+    /// Shorter than the manifest says, or an interrupted import.
+    Partial,
+}
+
+/// Expected checksums, keyed by model file name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModelManifest {
+    #[serde(default)]
+    pub models: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ManifestEntry {
+    /// Lowercase hex SHA-256 of the whole file.
+    pub sha256: String,
+    pub size_bytes: u64,
+    /// Modification time (Unix seconds) of the file when it last hashed clean,
+    /// so listing models does not have to re-hash gigabytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_modified: Option<u64>,
+    /// The checksum was taken from the imported file itself rather than
+    /// given, so it catches later changes but cannot vouch for the file.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub recorded: bool,
+}
+
+impl ModelManifest {
+    pub fn load(model_root: &Path) -> Result<Self> {
+        let path = model_root.join(MANIFEST_FILE);
+        match fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("invalid model manifest {}", path.display())),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error).with_context(|| format!("unable to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, model_root: &Path) -> Result<()> {
+        let path = model_root.join(MANIFEST_FILE);
+        let temp = model_root.join(format!("{MANIFEST_FILE}.tmp"));
+        fs::write(&temp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("unable to write {}", temp.display()))?;
+        fs::rename(&temp, &path).with_context(|| format!("unable to replace {}", path.display()))
+    }
+}
+
+/// Hyperparameters stored at the start of a whisper.cpp GGML model file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GgmlHeader {
+    pub n_vocab: i32,
+    pub n_audio_ctx: i32,
+    pub n_audio_state: i32,
+    pub n_audio_head: i32,
+    pub n_audio_layer: i32,
+    pub n_text_ctx: i32,
+    pub n_text_state: i32,
+    pub n_text_head: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    pub ftype: i32,
+}
+
+impl GgmlHeader {
+    pub fn multilingual(&self) -> bool {
+        self.n_vocab > ENGLISH_ONLY_VOCAB
+    }
+
+    pub fn model_type(&self) -> &'static str {
+        match (self.n_audio_layer, self.n_text_layer, self.n_mels) {
+            (4, _, _) => "tiny",
+            (6, _, _) => "base",
+            (12, _, _) => "small",
+            (24, _, _) => "medium",
+            (32, 4, 128) => "large-v3-turbo",
+            (32, _, 128) => "large-v3",
+            (32, _, _) => "large",
+            _ => "unknown",
+        }
+    }
+
+    pub fn quantization(&self) -> String {
+        let ftype = self.ftype % GGML_QNT_VERSION_FACTOR;
+        match ftype {
+            0 => "f32".to_string(),
+            1 => "f16".to_string(),
+            2 => "q4_0".to_string(),
+            3 => "q4_1".to_string(),
+            7 => "q8_0".to_string(),
+            8 => "q5_0".to_string(),
+            9 => "q5_1".to_string(),
+            10 => "q2_k".to_string(),
+            11 => "q3_k".to_string(),
+            12 => "q4_k".to_string(),
+            13 => "q5_k".to_string(),
+            14 => "q6_k".to_string(),
+            other => format!("ftype {other}"),
+        }
+    }
+}
+
+pub fn read_ggml_header(path: &Path) -> Result<GgmlHeader> {
+    let mut file =
+        File::open(path).with_context(|| format!("unable to open model {}", path.display()))?;
+    let mut bytes = [0u8; 48];
+    file.read_exact(&mut bytes)
+        .with_context(|| format!("model {} is too short for a GGML header", path.display()))?;
+
+    let field = |idx: usize| i32::from_le_bytes(bytes[idx * 4..idx * 4 + 4].try_into().unwrap());
+    let magic = field(0) as u32;
+    if magic != GGML_MAGIC {
+        anyhow::bail!("{} is not a GGML whisper model", path.display());
+    }
+
+    Ok(GgmlHeader {
+        n_vocab: field(1),
+        n_audio_ctx: field(2),
+        n_audio_state: field(3),
+        n_audio_head: field(4),
+        n_audio_layer: field(5),
+        n_text_ctx: field(6),
+        n_text_state: field(7),
+        n_text_head: field(8),
+        n_text_layer: field(9),
+        n_mels: field(10),
+        ftype: field(11),
+    })
+}
+
+/// Scans the model root for `ggml-*.bin` whisper models, sorted by size.
+/// Corrupt files and interrupted imports are listed too, with their status.
+pub fn scan_models(model_root: &Path) -> Vec<ModelInfo> {
+    let Ok(entries) = fs::read_dir(model_root) else {
+        return Vec::new();
+    };
+    let manifest = ModelManifest::load(model_root).unwrap_or_else(|error| {
+        eprintln!("ignoring model manifest: {error:#}");
+        ModelManifest::default()
+    });
+
+    let mut models = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(complete) = file_name.strip_suffix(PARTIAL_SUFFIX) {
+                let id = model_id_from_file_name(complete)?;
+                if model_root.join(complete).exists() {
+                    return None;
+                }
+                return Some(partial_model(id, file_name.clone(), &entry.path()));
+            }
+            let id = model_id_from_file_name(&file_name)?.to_string();
+            Some(describe_model(&entry.path(), id, file_name, &manifest))
+        })
+        .collect::<Vec<_>>();
+
+    models.sort_by(|a, b| a.size_bytes.cmp(&b.size_bytes).then(a.id.cmp(&b.id)));
+    models
+}
+
+pub fn find_model(model_root: &Path, model: &TranscriptionModel) -> Option<ModelInfo> {
+    scan_models(model_root)
+        .into_iter()
+        .find(|info| info.id == model.id())
+}
+
+/// Cheap pre-load check that turns truncated or foreign files into a clear
+/// error instead of a whisper.cpp load failure. Does not hash the file.
+pub fn check_model(model_root: &Path, model: &TranscriptionModel) -> Result<()> {
+    let Some(info) = find_model(model_root, model) else {
+        anyhow::bail!(
+            "missing whisper model {} in {}",
+            model.file_name(),
+            model_root.display()
+        );
+    };
+    if !info.usable() {
+        anyhow::bail!(
+            "whisper model {} cannot be loaded: {}",
+            info.file_name,
+            info.error.as_deref().unwrap_or("unknown problem")
+        );
+    }
+    Ok(())
+}
+
+/// Copies a model into the model root through a `.partial` file, checking it
+/// against the manifest entry and `expected_sha256` when there are any. A
+/// model with neither has its own checksum recorded and stays unverified.
+pub fn import_model(
+    model_root: &Path,
+    source: &Path,
+    expected_sha256: Option<&str>,
+) -> Result<ModelInfo> {
+    let file_name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("{} is not a model file", source.display()))?
+        .to_string();
+    let id = model_id_from_file_name(&file_name)
+        .with_context(|| format!("{file_name} is not named like a whisper model (ggml-<id>.bin)"))?
+        .to_string();
+    let expected_sha256 = expected_sha256
+        .map(str::trim)
+        .filter(|sha256| !sha256.is_empty())
+        .map(str::to_ascii_lowercase);
+    if let Some(sha256) = &expected_sha256 {
+        if sha256.len() != 64 || !sha256.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            anyhow::bail!("expected checksum must be 64 hex digits, got {sha256:?}");
+        }
+    }
+    read_ggml_header(source)?;
+
+    let target = model_root.join(&file_name);
+    if target.exists() {
+        anyhow::bail!("model {id} is already installed");
+    }
+    fs::create_dir_all(model_root)
+        .with_context(|| format!("unable to create {}", model_root.display()))?;
+    let mut manifest = ModelManifest::load(model_root)?;
+
+    let partial = partial_path(&target);
+    let (sha256, size_bytes) = match copy_hashed(source, &partial) {
+        Ok(copied) => copied,
+        Err(error) => {
+            let _ = fs::remove_file(&partial);
+            return Err(error);
+        }
+    };
+    // A given checksum overrides one this import recorded itself earlier.
+    let known = manifest
+        .models
+        .get(&file_name)
+        .filter(|entry| !(entry.recorded && expected_sha256.is_some()));
+    if let Some(expected) = known {
+        if expected.sha256 != sha256 || expected.size_bytes != size_bytes {
+            let _ = fs::remove_file(&partial);
+            anyhow::bail!(
+                "{file_name} does not match the manifest: expected sha256 {}, got {sha256}",
+                expected.sha256
+            );
+        }
+    }
+    if let Some(expected) = expected_sha256
+        .as_ref()
+        .filter(|expected| **expected != sha256)
+    {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("{file_name} does not match: expected sha256 {expected}, got {sha256}");
+    }
+    let recorded = expected_sha256.is_none() && known.map_or(true, |entry| entry.recorded);
+    fs::rename(&partial, &target)
+        .with_context(|| format!("unable to move model into {}", target.display()))?;
+
+    manifest.models.insert(
+        file_name.clone(),
+        ManifestEntry {
+            sha256,
+            size_bytes,
+            verified_modified: modified_secs(&target),
+            recorded,
+        },
+    );
+    manifest.save(model_root)?;
+    Ok(describe_model(&target, id, file_name, &manifest))
+}
+
+/// Hashes an installed model and compares it with the manifest.
+pub fn verify_model(model_root: &Path, model: &TranscriptionModel) -> Result<ModelInfo> {
+    let path = installed_path(model_root, model)?;
+    if !path.exists() {
+        anyhow::bail!("model {} is not installed", model.id());
+    }
+    let file_name = model.file_name();
+    let id = model.id().to_string();
+    let mut manifest = ModelManifest::load(model_root)?;
+
+    let info = describe_model(&path, id.clone(), file_name.clone(), &manifest);
+    if !info.usable() {
+        return Ok(info);
+    }
+    let Some(entry) = manifest.models.get_mut(&file_name) else {
+        return Ok(ModelInfo {
+            error: Some("no checksum for this model in the manifest".to_string()),
+            ..info
+        });
+    };
+
+    let (sha256, _) = hash_file(&path)?;
+    let matches = sha256 == entry.sha256;
+    let expected = entry.sha256.clone();
+    entry.verified_modified = if matches { modified_secs(&path) } else { None };
+    manifest.save(model_root)?;
+
+    let info = describe_model(&path, id, file_name, &manifest);
+    if matches && info.status == ModelStatus::Unverified {
+        return Ok(ModelInfo {
+            error: Some(
+                "unchanged since import, but no known checksum to check it against".to_string(),
+            ),
+            ..info
+        });
+    }
+    if matches {
+        return Ok(info);
+    }
+    Ok(ModelInfo {
+        status: ModelStatus::Corrupt,
+        error: Some(format!(
+            "checksum mismatch: expected sha256 {expected}, got {sha256}"
+        )),
+        ..info
+    })
+}
+
+/// Removes an installed model and any interrupted import of it. Its manifest
+/// checksum stays so a re-import is still verified.
+pub fn delete_model(model_root: &Path, model: &TranscriptionModel) -> Result<()> {
+    let path = installed_path(model_root, model)?;
+    let mut removed = false;
+    for candidate in [path.clone(), partial_path(&path)] {
+        match fs::remove_file(&candidate) {
+            Ok(()) => removed = true,
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("unable to delete {}", candidate.display()))
+            }
+        }
+    }
+    if !removed {
+        anyhow::bail!("model {} is not installed", model.id());
+    }
+
+    let mut manifest = ModelManifest::load(model_root)?;
+    if let Some(entry) = manifest.models.get_mut(&model.file_name()) {
+        entry.verified_modified = None;
+        manifest.save(model_root)?;
+    }
+    Ok(())
+}
+
+fn describe_model(
+    path: &Path,
+    id: String,
+    file_name: String,
+    manifest: &ModelManifest,
+) -> ModelInfo {
+    let metadata = fs::metadata(path).ok();
+    let size_bytes = metadata.as_ref().map(|meta| meta.len()).unwrap_or(0);
+    let mut info = ModelInfo {
+        id,
+        file_name,
+        size_bytes,
+        model_type: "unknown".to_string(),
+        multilingual: false,
+        quantization: "unknown".to_string(),
+        status: ModelStatus::Unverified,
+        error: None,
+    };
+
+    match read_ggml_header(path) {
+        Ok(header) => {
+            info.model_type = header.model_type().to_string();
+            info.multilingual = header.multilingual();
+            info.quantization = header.quantization();
+        }
+        Err(error) => {
+            info.status = ModelStatus::Corrupt;
+            info.error = Some(format!("{error:#}"));
+            return info;
+        }
+    }
+
+    let Some(entry) = manifest.models.get(&info.file_name) else {
+        return info;
+    };
+    if size_bytes < entry.size_bytes {
+        info.status = ModelStatus::Partial;
+        info.error = Some(format!(
+            "truncated: {size_bytes} of {} bytes",
+            entry.size_bytes
+        ));
+    } else if size_bytes > entry.size_bytes {
+        info.status = ModelStatus::Corrupt;
+        info.error = Some(format!("{size_bytes} bytes, expected {}", entry.size_bytes));
+    } else if !entry.recorded
+        && entry.verified_modified.is_some()
+        && entry.verified_modified == modified_secs(path)
+    {
+        info.status = ModelStatus::Verified;
+    }
+    info
+}
+
+fn partial_model(id: &str, file_name: String, path: &Path) -> ModelInfo {
+    ModelInfo {
+        id: id.to_string(),
+        file_name,
+        size_bytes: fs::metadata(path).map(|meta| meta.len()).unwrap_or(0),
+        model_type: "unknown".to_string(),
+        multilingual: false,
+        quantization: "unknown".to_string(),
+        status: ModelStatus::Partial,
+        error: Some("interrupted import or download".to_string()),
+    }
+}
+
+/// Rejects ids that would escape the model root once joined into a path.
+fn installed_path(model_root: &Path, model: &TranscriptionModel) -> Result<PathBuf> {
+    let id = model.id();
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        anyhow::bail!("invalid model id {id:?}");
+    }
+    Ok(model_root.join(model.file_name()))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+fn copy_hashed(source: &Path, target: &Path) -> Result<(String, u64)> {
+    let mut writer =
+        File::create(target).with_context(|| format!("unable to create {}", target.display()))?;
+    let hashed = hash_into(source, &mut writer)?;
+    writer
+        .sync_all()
+        .with_context(|| format!("unable to flush {}", target.display()))?;
+    Ok(hashed)
+}
+
+fn hash_file(path: &Path) -> Result<(String, u64)> {
+    hash_into(path, &mut std::io::sink())
+}
+
+/// Streams `path` into `writer`, returning its hex SHA-256 and length.
+fn hash_into(path: &Path, writer: &mut dyn Write) -> Result<(String, u64)> {
+    let mut reader =
+        File::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; COPY_CHUNK_BYTES];
+    let mut size_bytes = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .with_context(|| format!("unable to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        writer
+            .write_all(&buf[..read])
+            .context("unable to write model copy")?;
+        size_bytes += read as u64;
+    }
+    Ok((hex(&hasher.finalize()), size_bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn model_id_from_file_name(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_prefix("ggml-")?
+        .strip_suffix(".bin")
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{
+        check_model, delete_model, hash_file, import_model, model_id_from_file_name, scan_models,
+        verify_model, GgmlHeader, ManifestEntry, ModelManifest, ModelStatus, GGML_MAGIC,
+    };
+    use crate::core::{EngineSettings, TranscriptionModel};
+
+    fn header_bytes(
+        n_vocab: i32,
+        audio_layers: i32,
+        text_layers: i32,
+        n_mels: i32,
+        ftype: i32,
+    ) -> Vec<u8> {
+        let fields = [
+            GGML_MAGIC as i32,
+            n_vocab,
+            1500,
+            512,
+            8,
+            audio_layers,
+            448,
+            512,
+            8,
+            text_layers,
+            n_mels,
+            ftype,
+        ];
+        let mut bytes = fields
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        bytes.extend_from_slice(&[0u8; 64]);
+        bytes
+    }
+
+    fn temp_model_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("lumitype-models-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn scan_describes_models_from_ggml_header() {
+        let root = temp_model_root("scan");
+        fs::write(
+            root.join("ggml-base.en.bin"),
+            header_bytes(51_864, 6, 6, 80, 1),
+        )
+        .unwrap();
+        fs::write(
+            root.join("ggml-large-v3-turbo-q5_0.bin"),
+            header_bytes(51_866, 32, 4, 128, 1008),
+        )
+        .unwrap();
+        fs::write(root.join("ggml-broken.bin"), b"not a model").unwrap();
+        fs::write(root.join("porcupine_params.pv"), b"other asset").unwrap();
+
+        let models = scan_models(&root);
+        assert_eq!(models.len(), 3);
+
+        let broken = models.iter().find(|m| m.id == "broken").unwrap();
+        assert_eq!(broken.status, ModelStatus::Corrupt);
+        assert!(!broken.usable());
+
+        let base = models.iter().find(|m| m.id == "base.en").unwrap();
+        assert_eq!(base.model_type, "base");
+        assert!(!base.multilingual);
+        assert_eq!(base.quantization, "f16");
+
+        let turbo = models
+            .iter()
+            .find(|m| m.id == "large-v3-turbo-q5_0")
+            .unwrap();
+        assert_eq!(turbo.model_type, "large-v3-turbo");
+        assert!(turbo.multilingual);
+        assert_eq!(turbo.quantization, "q5_0");
+        assert_eq!(
+            TranscriptionModel::new(turbo.id.clone()).file_name(),
+            "ggml-large-v3-turbo-q5_0.bin"
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn import_verifies_and_records_checksums() {
+        let root = temp_model_root("import");
+        let source_dir = temp_model_root("import-source");
+        let source = source_dir.join("ggml-tiny.en.bin");
+        fs::write(&source, header_bytes(51_864, 4, 4, 80, 1)).unwrap();
+
+        // Nothing to check an unknown file against, so it is not vouched for.
+        let imported = import_model(&root, &source, None).unwrap();
+        assert_eq!(imported.id, "tiny.en");
+        assert_eq!(imported.status, ModelStatus::Unverified);
+        assert!(!root.join("ggml-tiny.en.bin.partial").exists());
+        assert!(import_model(&root, &source, None).is_err());
+
+        let model = TranscriptionModel::new("tiny.en");
+        let verified = verify_model(&root, &model).unwrap();
+        assert_eq!(verified.status, ModelStatus::Unverified);
+        assert!(verified.error.unwrap().contains("no known checksum"));
+
+        // Same length, different bytes: only the checksum catches it.
+        let mut tampered = header_bytes(51_864, 4, 4, 80, 1);
+        *tampered.last_mut().unwrap() = 1;
+        fs::write(root.join("ggml-tiny.en.bin"), &tampered).unwrap();
+        let verified = verify_model(&root, &model).unwrap();
+        assert_eq!(verified.status, ModelStatus::Corrupt);
+        assert!(verified.error.unwrap().contains("checksum mismatch"));
+
+        delete_model(&root, &model).unwrap();
+        assert!(scan_models(&root).is_empty());
+        assert!(delete_model(&root, &model).is_err());
+        assert!(delete_model(&root, &TranscriptionModel::new("../tiny.en")).is_err());
+
+        // The manifest still knows the checksum, so a different file is refused.
+        fs::write(&source, &tampered[..tampered.len() - 1]).unwrap();
+        assert!(import_model(&root, &source, None)
+            .unwrap_err()
+            .to_string()
+            .contains("does not match the manifest"));
+        assert!(!root.join("ggml-tiny.en.bin.partial").exists());
+
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(source_dir).unwrap();
+    }
+
+    #[test]
+    fn import_with_an_expected_checksum_is_verified() {
+        let root = temp_model_root("import-expected");
+        let source_dir = temp_model_root("import-expected-source");
+        let source = source_dir.join("ggml-tiny.en.bin");
+        let bytes = header_bytes(51_864, 4, 4, 80, 1);
+        fs::write(&source, &bytes).unwrap();
+        let (sha256, _) = hash_file(&source).unwrap();
+
+        // A truncated download still has a valid header; the checksum catches it.
+        let truncated = source_dir.join("truncated").join("ggml-tiny.en.bin");
+        fs::create_dir_all(truncated.parent().unwrap()).unwrap();
+        fs::write(&truncated, &bytes[..bytes.len() - 8]).unwrap();
+        assert!(import_model(&root, &truncated, Some(&sha256))
+            .unwrap_err()
+            .to_string()
+            .contains("does not match"));
+        assert!(scan_models(&root).is_empty());
+        assert!(import_model(&root, &source, Some("not-a-checksum")).is_err());
+
+        let imported = import_model(&root, &source, Some(&sha256.to_uppercase())).unwrap();
+        assert_eq!(imported.status, ModelStatus::Verified);
+        let model = TranscriptionModel::new("tiny.en");
+        let verified = verify_model(&root, &model).unwrap();
+        assert_eq!(verified.status, ModelStatus::Verified);
+        assert_eq!(verified.error, None);
+
+        fs::remove_dir_all(root).unwrap();
+        fs::remove_dir_all(source_dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_and_interrupted_models_are_reported() {
+        let root = temp_model_root("partial");
+        let bytes = header_bytes(51_864, 6, 6, 80, 1);
+        fs::write(root.join("ggml-base.en.bin"), &bytes[..bytes.len() - 10]).unwrap();
+        fs::write(root.join("ggml-small.bin.partial"), &bytes[..20]).unwrap();
+        let mut manifest = ModelManifest::default();
+        manifest.models.insert(
+            "ggml-base.en.bin".to_string(),
+            ManifestEntry {
+                sha256: "0".repeat(64),
+                size_bytes: bytes.len() as u64,
+                verified_modified: None,
+                recorded: false,
+            },
+        );
+        manifest.save(&root).unwrap();
+
+        let models = scan_models(&root);
+        let statuses = models
+            .iter()
+            .map(|m| (m.id.as_str(), m.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ("small", ModelStatus::Partial),
+                ("base.en", ModelStatus::Partial)
+            ]
+        );
+
+        let error = check_model(&root, &TranscriptionModel::base_en()).unwrap_err();
+        assert!(error.to_string().contains("truncated"), "{error}");
+        assert!(check_model(&root, &TranscriptionModel::new("medium")).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn quantization_strips_quantization_version() {
+        let header = GgmlHeader {
+            n_vocab: 51_865,
+            n_audio_ctx: 1500,
+            n_audio_state: 768,
+            n_audio_head: 12,
+            n_audio_layer: 12,
+            n_text_ctx: 448,
+            n_text_state: 768,
+            n_text_head: 12,
+            n_text_layer: 12,
+            n_mels: 80,
+            ftype: 2007,
+        };
+        assert_eq!(header.quantization(), "q8_0");
+        assert_eq!(header.model_type(), "small");
+    }
+
+    #[test]
+    fn model_ids_come_from_ggml_file_names() {
+        assert_eq!(
+            model_id_from_file_name("ggml-small.en.bin"),
+            Some("small.en")
+        );
+        assert_eq!(model_id_from_file_name("ggml-.bin"), None);
+        assert_eq!(model_id_from_file_name("porcupine_params.pv"), None);
+    }
+
+    #[test]
+    fn legacy_enum_settings_map_to_catalog_ids() {
+        let settings: EngineSettings = serde_json::from_str(r#"{"model":"tiny_en"}"#).unwrap();
+        assert_eq!(settings.model, TranscriptionModel::new("tiny.en"));
+
+        let encoded = serde_json::to_string(&TranscriptionModel::base_en()).unwrap();
+        assert_eq!(encoded, "\"base.en\"");
+    }
+}
@@ -0,0 +1,243 @@
+/// How a spoken symbol joins the words around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spacing {
+    /// Attached to the previous word: `word, next`.
+    Trailing,
+    /// Attached to the next word: `word (next`.
+    Leading,
+    /// A line break, with no spaces on either side.
+    Break,
+}
+
+/// Spoken phrase, the text it stands for and how that text is spaced.
+type Command = (&'static str, &'static str, Spacing);
+
+/// Longer phrases come first so "new paragraph" wins over a shorter match.
+const COMMANDS: &[Command] = &[
+    ("open parenthesis", "(", Spacing::Leading),
+    ("close parenthesis", ")", Spacing::Trailing),
+    ("exclamation point", "!", Spacing::Trailing),
+    ("exclamation mark", "!", Spacing::Trailing),
+    ("question mark", "?", Spacing::Trailing),
+    ("new paragraph", "\n\n", Spacing::Break),
+    ("open paren", "(", Spacing::Leading),
+    ("close paren", ")", Spacing::Trailing),
+    ("open quote", "\"", Spacing::Leading),
+    ("close quote", "\"", Spacing::Trailing),
+    ("end quote", "\"", Spacing::Trailing),
+    ("semi colon", ";", Spacing::Trailing),
+    ("full stop", ".", Spacing::Trailing),
+    ("new line", "\n", Spacing::Break),
+    ("newline", "\n", Spacing::Break),
+    ("semicolon", ";", Spacing::Trailing),
+    ("ellipsis", "...", Spacing::Trailing),
+    ("period", ".", Spacing::Trailing),
+    ("comma", ",", Spacing::Trailing),
+    ("colon", ":", Spacing::Trailing),
+];
+
+/// Said before a command phrase to type the phrase itself.
+const LITERAL: &str = "literal";
+
+/// Symbols that close a sentence or clause. Their names are also ordinary
+/// words ("the trial period", "colon cancer"), so they only apply where the
+/// speaker ended a phrase.
+const PHRASE_ENDS: &[&str] = &[".", "?", "!", ":", "..."];
+
+/// Punctuation replaced by a spoken symbol that directly follows it.
+const SENTENCE_PUNCTUATION: &[char] = &[',', '.', '!', '?', ';', ':'];
+
+/// Punctuation whisper attaches to words; ignored when matching phrases.
+const WORD_PUNCTUATION: &[char] = &[',', '.', '!', '?', ';', ':', '"', '\''];
+
+/// Replaces spoken punctuation ("comma", "question mark") and layout commands
+/// ("new line", "new paragraph") with the symbols and line breaks they name.
+///
+/// Whisper often punctuates around the command word itself ("Hello, comma,
+/// world."), so punctuation it put directly before a spoken symbol is
+/// dropped in favour of the spoken one. Sentence-ending symbols are only
+/// typed at the end of a phrase; see [`ends_phrase`].
+pub fn apply_spoken_punctuation(text: &str) -> String {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let mut out = String::new();
+    // The next word attaches without a space (after an opening symbol).
+    let mut glue = false;
+    let mut capitalize = false;
+    let mut idx = 0;
+
+    while idx < words.len() {
+        if normalized(words[idx]) == LITERAL {
+            if let Some((_, len)) = command_at(&words[idx + 1..]) {
+                for word in &words[idx + 1..idx + 1 + len] {
+                    push_word(&mut out, word, &mut glue, &mut capitalize);
+                }
+                idx += 1 + len;
+                continue;
+            }
+        }
+
+        let command = command_at(&words[idx..]).filter(|(&(_, symbol, _), len)| {
+            !PHRASE_ENDS.contains(&symbol) || ends_phrase(&words[idx..], *len)
+        });
+        let Some((&(_, symbol, spacing), len)) = command else {
+            push_word(&mut out, words[idx], &mut glue, &mut capitalize);
+            idx += 1;
+            continue;
+        };
+        idx += len;
+
+        match spacing {
+            Spacing::Trailing => {
+                if symbol != "\"" && symbol != ")" {
+                    let kept = out.trim_end_matches(SENTENCE_PUNCTUATION).len();
+                    out.truncate(kept);
+                }
+                out.push_str(symbol);
+                glue = false;
+                capitalize |= matches!(symbol, "." | "?" | "!");
+            }
+            Spacing::Leading => {
+                if needs_space(&out, glue) {
+                    out.push(' ');
+                }
+                out.push_str(symbol);
+                glue = true;
+            }
+            Spacing::Break => {
+                out.push_str(symbol);
+                glue = true;
+                capitalize = true;
+            }
+        }
+    }
+    out
+}
+
+fn push_word(out: &mut String, word: &str, glue: &mut bool, capitalize: &mut bool) {
+    if needs_space(out, *glue) {
+        out.push(' ');
+    }
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+        if *capitalize {
+            out.extend(first.to_uppercase());
+        } else {
+            out.push(first);
+        }
+        out.extend(chars);
+    }
+    *glue = false;
+    *capitalize = false;
+}
+
+fn needs_space(out: &str, glue: bool) -> bool {
+    !glue && !out.is_empty() && !out.ends_with('\n')
+}
+
+/// The command spoken at the start of `words`, and how many words it spans.
+fn command_at(words: &[&str]) -> Option<(&'static Command, usize)> {
+    COMMANDS.iter().find_map(|command| {
+        let phrase = command.0.split(' ').collect::<Vec<_>>();
+        let matches = words.len() >= phrase.len()
+            && phrase
+                .iter()
+                .zip(words)
+                .all(|(expected, word)| normalized(word) == *expected);
+        matches.then_some((command, phrase.len()))
+    })
+}
+
+/// Whether the command spoken as `words[..len]` ends a phrase: nothing or
+/// another command follows, whisper punctuated it, or the next word starts a
+/// sentence. "I" is capitalized anywhere, so it does not count.
+fn ends_phrase(words: &[&str], len: usize) -> bool {
+    let Some(next) = words.get(len) else {
+        return true;
+    };
+    let pronoun = normalized(next) == "i" || normalized(next).starts_with("i'");
+    words[len - 1].ends_with(SENTENCE_PUNCTUATION)
+        || command_at(&words[len..]).is_some()
+        || (next.starts_with(char::is_uppercase) && !pronoun)
+}
+
+/// `word` lowercased without the punctuation whisper attached to it.
+pub(crate) fn normalized(word: &str) -> String {
+    word.trim_matches(WORD_PUNCTUATION).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_spoken_punctuation;
+
+    #[test]
+    fn spoken_punctuation_table() {
+        let cases = [
+            // Plain symbols.
+            ("hello comma world", "hello, world"),
+            ("is it done question mark", "is it done?"),
+            ("ship it exclamation mark", "ship it!"),
+            ("ship it exclamation point", "ship it!"),
+            ("that is all period", "that is all."),
+            ("that is all full stop", "that is all."),
+            ("three things colon Eggs", "three things: Eggs"),
+            ("first semicolon second", "first; second"),
+            ("first semi colon second", "first; second"),
+            ("wait ellipsis", "wait..."),
+            // Sentence ends capitalize the next word.
+            ("done period. next one", "done. Next one"),
+            ("really question mark Yes", "really? Yes"),
+            ("done period new line next", "done.\nNext"),
+            // Sentence-ending names used as ordinary words.
+            ("trial period ended", "trial period ended"),
+            ("colon cancer screening", "colon cancer screening"),
+            ("the period I mentioned", "the period I mentioned"),
+            ("a question mark over it", "a question mark over it"),
+            // Whisper's own punctuation around the spoken command.
+            ("Hello, comma, world.", "Hello, world."),
+            ("Is it done? Question mark.", "Is it done?"),
+            ("Okay. Period.", "Okay."),
+            ("Dear Sam, colon.", "Dear Sam:"),
+            ("Hello COMMA world", "Hello, world"),
+            // Layout commands.
+            ("first new line second", "first\nSecond"),
+            ("first newline second", "first\nSecond"),
+            ("first new paragraph second", "first\n\nSecond"),
+            (
+                "Thanks. New paragraph. Best regards.",
+                "Thanks.\n\nBest regards.",
+            ),
+            ("new line hello", "\nHello"),
+            ("end new line", "end\n"),
+            // Brackets and quotes.
+            ("see open paren below close paren", "see (below)"),
+            (
+                "see open parenthesis below close parenthesis",
+                "see (below)",
+            ),
+            ("he said open quote hi close quote", "he said \"hi\""),
+            ("he said open quote hi end quote period", "he said \"hi\"."),
+            ("call open paren", "call ("),
+            // Escaping and text without commands.
+            ("type the word literal comma", "type the word comma"),
+            ("the literal new line character", "the new line character"),
+            ("literal is a word too", "literal is a word too"),
+            ("no commands here", "no commands here"),
+            ("  extra   spaces  ", "extra spaces"),
+            ("", ""),
+            ("comma", ","),
+            ("new", "new"),
+            ("question", "question"),
+        ];
+        for (spoken, typed) in cases {
+            assert_eq!(apply_spoken_punctuation(spoken), typed, "{spoken:?}");
+        }
+    }
+
+    #[test]
+    fn partial_commands_settle_once_complete() {
+        assert_eq!(apply_spoken_punctuation("done question"), "done question");
+        assert_eq!(apply_spoken_punctuation("done question mark"), "done?");
+        assert_eq!(apply_spoken_punctuation("notes new"), "notes new");
+        assert_eq!(apply_spoken_punctuation("notes new paragraph"), "notes\n\n");
+    }
+}
@@ -0,0 +1,436 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::vocabulary::{levenshtein, squash};
+
+/// A spoken phrase and the text typed in its place.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Snippet {
+    /// What is said, such as "insert my signature".
+    pub phrase: String,
+    /// What is typed; may span several lines and use `{date}` and `{time}`.
+    pub text: String,
+}
+
+/// Trims phrases and drops snippets without a phrase or text, and later
+/// snippets whose phrase differs from an earlier one only in case or spacing.
+pub fn normalize_snippets(snippets: &[Snippet]) -> Vec<Snippet> {
+    let mut seen = Vec::new();
+    let mut out = Vec::new();
+    for snippet in snippets {
+        let phrase = snippet
+            .phrase
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let key = squash(&phrase);
+        if key.is_empty() || snippet.text.trim().is_empty() || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        out.push(Snippet {
+            phrase,
+            text: snippet.text.clone(),
+        });
+    }
+    out
+}
+
+/// Reads a snippet file written by [`write_snippets`].
+pub fn read_snippets(path: &Path) -> Result<Vec<Snippet>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let snippets = serde_json::from_str::<Vec<Snippet>>(&content)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    Ok(normalize_snippets(&snippets))
+}
+
+pub fn write_snippets(path: &Path, snippets: &[Snippet]) -> Result<()> {
+    let content = serde_json::to_string_pretty(snippets).context("failed to encode snippets")?;
+    fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// `imported` added to `current`; an imported snippet replaces a current one
+/// with the same phrase.
+pub fn merge_snippets(current: &[Snippet], imported: &[Snippet]) -> Vec<Snippet> {
+    let replaced = imported
+        .iter()
+        .map(|snippet| squash(&snippet.phrase))
+        .collect::<Vec<_>>();
+    let kept = current
+        .iter()
+        .filter(|snippet| !replaced.contains(&squash(&snippet.phrase)));
+    normalize_snippets(&kept.chain(imported).cloned().collect::<Vec<_>>())
+}
+
+/// Wall-clock date and time used for `{date}` and `{time}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl LocalTime {
+    /// The current time in the system time zone (`TZ` or the system default
+    /// on Linux); UTC where the zone cannot be read.
+    pub fn now() -> Self {
+        let unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or(0);
+        Self::at(unix, utc_offset_seconds())
+    }
+
+    /// The wall-clock time at Unix time `unix` in a zone `offset` seconds
+    /// east of UTC.
+    pub fn at(unix: i64, offset: i64) -> Self {
+        Self::from_unix(unix + offset)
+    }
+
+    /// The calendar date and time `seconds` after 1970-01-01 00:00.
+    pub fn from_unix(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86_400);
+        let of_day = seconds.rem_euclid(86_400) as u32;
+        // Howard Hinnant's days-to-civil conversion, with eras of 400 years.
+        let shifted = days + 719_468;
+        let era = shifted.div_euclid(146_097);
+        let day_of_era = shifted.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self {
+            year,
+            month,
+            day,
+            hour: of_day / 3_600,
+            minute: of_day % 3_600 / 60,
+        }
+    }
+}
+
+fn utc_offset_seconds() -> i64 {
+    #[cfg(target_os = "macos")]
+    {
+        use objc::{class, msg_send, runtime::Object, sel, sel_impl};
+
+        // SAFETY: plain Foundation getters on the shared local time zone.
+        unsafe {
+            let zone: *mut Object = msg_send![class!(NSTimeZone), localTimeZone];
+            if zone.is_null() {
+                return 0;
+            }
+            let offset: isize = msg_send![zone, secondsFromGMT];
+            offset as i64
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as libc::time_t)
+            .unwrap_or(0);
+        extern "C" {
+            // POSIX, but not exported by `libc` for every target.
+            fn tzset();
+        }
+        let mut local = std::mem::MaybeUninit::<libc::tm>::zeroed();
+        // SAFETY: `tzset` rereads `TZ` so a changed zone is picked up, and
+        // `localtime_r` only writes the `tm` it is given.
+        unsafe {
+            tzset();
+            if libc::localtime_r(&now, local.as_mut_ptr()).is_null() {
+                return 0;
+            }
+            // `c_long` is only 32 bits on some targets.
+            #[allow(clippy::unnecessary_cast)]
+            let offset = local.assume_init().tm_gmtoff as i64;
+            offset
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        0
+    }
+}
+
+/// Fills in `{date}` (2026-03-14) and `{time}` (09:05); other braces are
+/// typed as written.
+pub fn expand_placeholders(text: &str, now: LocalTime) -> String {
+    let date = format!("{:04}-{:02}-{:02}", now.year, now.month, now.day);
+    let time = format!("{:02}:{:02}", now.hour, now.minute);
+    text.replace("{date}", &date).replace("{time}", &time)
+}
+
+/// Snippets ready to match against transcripts, longest phrase first so
+/// "insert my work address" wins over "insert my address".
+#[derive(Debug, Clone, Default)]
+pub struct SnippetTable {
+    snippets: Vec<(Snippet, String, usize)>,
+}
+
+impl SnippetTable {
+    pub fn new(snippets: &[Snippet]) -> Self {
+        let mut snippets = normalize_snippets(snippets)
+            .into_iter()
+            .map(|snippet| {
+                let key = squash(&snippet.phrase);
+                let words = snippet.phrase.split_whitespace().count();
+                (snippet, key, words)
+            })
+            .collect::<Vec<_>>();
+        snippets.sort_by_key(|(_, _, words)| std::cmp::Reverse(*words));
+        Self { snippets }
+    }
+
+    /// Replaces every snippet phrase in `text` with its expansion. Phrases
+    /// match despite whisper's punctuation, casing and small mishearings.
+    /// The punctuation whisper put after a phrase is dropped when the phrase
+    /// ends the text, so a closing period does not trail a signature.
+    pub fn expand(&self, text: &str, now: LocalTime) -> String {
+        if self.snippets.is_empty() {
+            return text.to_string();
+        }
+        let words = word_ranges(text);
+        let mut out = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut idx = 0;
+        'words: while idx < words.len() {
+            for (snippet, key, phrase_words) in &self.snippets {
+                // One word fewer covers whisper joining words ("timestamp"); a
+                // longer span would let the phrase swallow a neighbouring word.
+                let shortest = phrase_words.saturating_sub(1).max(1);
+                for span in (shortest..=*phrase_words).rev() {
+                    let Some(candidate) = words.get(idx..idx + span) else {
+                        continue;
+                    };
+                    let (start, end) = (candidate[0].0, candidate[span - 1].1);
+                    let spoken = &text[start..end];
+                    if spoken.contains('\n') || !is_close(&squash(spoken), key) {
+                        continue;
+                    }
+                    let punctuation = |ch: char| !ch.is_alphanumeric();
+                    let body_start = end - spoken.trim_start_matches(punctuation).len();
+                    let body_end = start + spoken.trim_end_matches(punctuation).len();
+                    out.push_str(&text[copied..body_start]);
+                    out.push_str(&expand_placeholders(&snippet.text, now));
+                    idx += span;
+                    copied = if idx == words.len() { end } else { body_end };
+                    continue 'words;
+                }
+            }
+            idx += 1;
+        }
+        out.push_str(&text[copied..]);
+        out
+    }
+}
+
+/// Byte ranges of the whitespace-separated words of `text`.
+fn word_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (idx, ch) in text.char_indices() {
+        match (ch.is_whitespace(), start) {
+            (true, Some(from)) => {
+                ranges.push((from, idx));
+                start = None;
+            }
+            (false, None) => start = Some(idx),
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        ranges.push((from, text.len()));
+    }
+    ranges
+}
+
+/// Within one edit per five letters of the phrase; short phrases must match
+/// exactly.
+fn is_close(candidate: &str, key: &str) -> bool {
+    let allowed = key.chars().count() / 5;
+    !candidate.is_empty()
+        && candidate.chars().count().abs_diff(key.chars().count()) <= allowed
+        && levenshtein(candidate, key) <= allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_snippets, normalize_snippets, LocalTime, Snippet, SnippetTable};
+
+    fn snippet(phrase: &str, text: &str) -> Snippet {
+        Snippet {
+            phrase: phrase.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    const NOW: LocalTime = LocalTime {
+        year: 2026,
+        month: 3,
+        day: 14,
+        hour: 9,
+        minute: 5,
+    };
+
+    #[test]
+    fn snippet_expansion_table() {
+        let table = SnippetTable::new(&[
+            snippet("insert my signature", "Best regards,\nSam Lee"),
+            snippet("my address", "1 Main Street"),
+            snippet("my work address", "2 Office Park"),
+            snippet("today's date", "{date}"),
+            snippet("time stamp", "[{date} {time}] {unknown}"),
+            snippet("sign off", "Cheers"),
+        ]);
+        let cases = [
+            ("Insert my signature.", "Best regards,\nSam Lee"),
+            (
+                "Thanks! Insert my signature.",
+                "Thanks! Best regards,\nSam Lee",
+            ),
+            // Whisper mishearings and punctuation inside the phrase.
+            ("Insert the signature.", "Best regards,\nSam Lee"),
+            ("Insert my, signature.", "Best regards,\nSam Lee"),
+            (
+                "Send it to my address, please.",
+                "Send it to 1 Main Street, please.",
+            ),
+            ("Send it to my work address.", "Send it to 2 Office Park"),
+            ("Today's date.", "2026-03-14"),
+            ("Note: time stamp.", "Note: [2026-03-14 09:05] {unknown}"),
+            ("Timestamp", "[2026-03-14 09:05] {unknown}"),
+            ("Okay, sign off.", "Okay, Cheers"),
+            ("Insert my\nsignature.", "Insert my\nsignature."),
+            ("Nothing to expand.", "Nothing to expand."),
+            ("", ""),
+        ];
+        for (spoken, typed) in cases {
+            assert_eq!(table.expand(spoken, NOW), typed, "{spoken:?}");
+        }
+    }
+
+    #[test]
+    fn short_phrases_match_exactly() {
+        let table = SnippetTable::new(&[snippet("sig", "Sam")]);
+        assert_eq!(table.expand("Sig.", NOW), "Sam");
+        assert_eq!(table.expand("Big.", NOW), "Big.");
+    }
+
+    #[test]
+    fn unix_seconds_convert_to_calendar_dates() {
+        let at = |year, month, day, hour, minute| LocalTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+        };
+        assert_eq!(LocalTime::from_unix(0), at(1970, 1, 1, 0, 0));
+        assert_eq!(LocalTime::from_unix(951_782_400), at(2000, 2, 29, 0, 0));
+        assert_eq!(LocalTime::from_unix(1_773_479_100), at(2026, 3, 14, 9, 5));
+        assert_eq!(LocalTime::from_unix(-60), at(1969, 12, 31, 23, 59));
+    }
+
+    #[test]
+    fn zone_offsets_shift_the_wall_clock() {
+        let unix = 1_773_479_100;
+        assert_eq!(LocalTime::at(unix, 0), NOW);
+        assert_eq!(
+            LocalTime::at(unix, 5 * 3_600 + 30 * 60),
+            LocalTime {
+                hour: 14,
+                minute: 35,
+                ..NOW
+            }
+        );
+        assert_eq!(
+            LocalTime::at(unix, -10 * 3_600),
+            LocalTime {
+                day: 13,
+                hour: 23,
+                ..NOW
+            }
+        );
+    }
+
+    /// Run by `now_follows_the_tz_zone` in a child process.
+    #[test]
+    #[ignore]
+    fn print_local_time() {
+        println!("local_time={:?}", LocalTime::now());
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn now_follows_the_tz_zone() {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let unix = || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64
+        };
+        // `TZ` is process-wide, so only a child test process gets the zone:
+        // five and a half hours east of UTC, moving the hour and the minute.
+        let test = format!(
+            "{}::print_local_time",
+            module_path!().split_once("::").unwrap().1
+        );
+        let before = unix();
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", &test, "--ignored", "--nocapture"])
+            .env("TZ", "IST-5:30")
+            .output()
+            .unwrap();
+        let after = unix();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let offset = 5 * 3_600 + 30 * 60;
+        assert!(
+            [before, after]
+                .iter()
+                .any(|&unix| stdout
+                    .contains(&format!("local_time={:?}", LocalTime::at(unix, offset)))),
+            "{stdout}"
+        );
+    }
+
+    #[test]
+    fn normalize_and_merge_dedupe_by_phrase() {
+        let current = vec![
+            snippet(" my  address ", "old"),
+            snippet("My Address", "duplicate"),
+            snippet("sign off", "Cheers"),
+            snippet("empty", "  "),
+        ];
+        assert_eq!(
+            normalize_snippets(&current),
+            vec![snippet("my address", "old"), snippet("sign off", "Cheers")]
+        );
+        let merged = merge_snippets(&current, &[snippet("my address", "new")]);
+        assert_eq!(
+            merged,
+            vec![snippet("sign off", "Cheers"), snippet("my address", "new")]
+        );
+    }
+}
@@ -1,6 +1,16 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use super::{permissions::PermissionStatus, AudioFrame, TranscriptionModel};
+use super::{
+    cadence::DecodeStats,
+    command_mode::{CommandSet, KeyChord},
+    health::{Component, ComponentHealth, Health},
+    permissions::PermissionStatus,
+    snippets::Snippet,
+    transcriber::{FinalTranscript, ModelLoadStatus},
+    voice_commands::VoiceCommand,
+    AudioFrame, DecodingProfile, HttpBackendSettings, ModelLoadPolicy, TranscriptionBackendKind,
+    TranscriptionModel,
+};
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
 pub enum DictationState {
@@ -10,6 +20,58 @@ pub enum DictationState {
     Stopping,
 }
 
+/// Whether a session types what was said, its English translation, or
+/// presses the keys of a spoken command.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DictationMode {
+    #[default]
+    Transcribe,
+    Translate,
+    /// The utterance is matched against the command grammar and pressed as
+    /// keys instead of typed.
+    Command,
+}
+
+/// Change from one transcript hypothesis to the next: erase `retract`
+/// characters from the end, then type `insert`.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct TranscriptEdit {
+    pub retract: usize,
+    pub insert: String,
+}
+
+impl TranscriptEdit {
+    /// The smallest edit that turns `previous` into `next`.
+    pub fn between(previous: &str, next: &str) -> Self {
+        let mut prefix_len = 0usize;
+        for (a, b) in previous.chars().zip(next.chars()) {
+            if a != b {
+                break;
+            }
+            prefix_len += a.len_utf8();
+        }
+
+        Self {
+            retract: previous[prefix_len..].chars().count(),
+            insert: next[prefix_len..].to_string(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.retract == 0 && self.insert.is_empty()
+    }
+
+    pub fn apply(&self, text: &mut String) {
+        for _ in 0..self.retract {
+            if text.pop().is_none() {
+                break;
+            }
+        }
+        text.push_str(&self.insert);
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
 pub enum TrayState {
     Idle,
@@ -22,15 +84,39 @@ pub enum EngineCommand {
     AudioFrame(AudioFrame),
     WakeDetected,
     PushToTalkTriggered,
+    TranslateTriggered,
+    CommandTriggered,
     SilenceTimeout,
-    TranscriptionDelta(String),
+    TranscriptionEdit(TranscriptEdit),
+    TranscriptFinalized(FinalTranscript),
+    LanguageDetected(String),
     TranscriptionFinished,
+    /// An editing command spoken in the session that just ended.
+    VoiceCommand(VoiceCommand),
+    PressKeys(Vec<KeyChord>),
+    ReportError(String),
+    ReportHealth(Component, Health),
+    ReportDecodeStats(DecodeStats),
+    ModelLoadChanged(ModelLoadStatus),
     CancelDictation,
     UndoLastDictation,
     SetEnabled(bool),
     UpdateMicrophone(String),
     UpdateSensitivity(f32),
-    UpdateModel(TranscriptionModel),
+    UpdateModel(TranscriptionModel, Option<TranscriptionModel>),
+    UpdateBackend(TranscriptionBackendKind, HttpBackendSettings),
+    UpdateLanguage(String),
+    UpdateDefaultMode(DictationMode),
+    UpdateVocabulary(Vec<String>, bool),
+    UpdateBlocklist(Vec<String>),
+    UpdateDecoding(DecodingProfile, DecodingProfile),
+    UpdateThreads(u32),
+    UpdateLoadPolicy(ModelLoadPolicy, u32),
+    UpdateContextCarry(bool, u32),
+    UpdateSpokenPunctuation(bool),
+    UpdateVoiceCommands(bool),
+    UpdateSnippets(Vec<Snippet>),
+    UpdateCommands(CommandSet),
     PermissionsChecked(PermissionStatus),
 }
 
@@ -41,9 +127,15 @@ pub enum EngineEvent {
     TrayStateChanged(TrayState),
     OverlayVisibility(bool),
     OverlayReset,
-    OverlayTextDelta(String),
+    DictationModeChanged(DictationMode),
+    OverlayTextEdit(TranscriptEdit),
+    TranscriptFinalized(FinalTranscript),
     OverlayWave(f32),
+    LanguageDetected(String),
     PermissionsRequired(PermissionStatus),
+    HealthChanged(ComponentHealth),
+    DecodeStatsChanged(DecodeStats),
+    ModelLoadChanged(ModelLoadStatus),
     Error(String),
 }
 
@@ -0,0 +1,642 @@
+use super::transcriber::TimedWord;
+
+const SAMPLES_PER_MS: usize = 16;
+/// Committed text passed back to the decoder as prompt context.
+const PROMPT_CHARS: usize = 200;
+/// Longest run of already-committed words a new hypothesis may repeat.
+const MAX_OVERLAP_WORDS: usize = 5;
+/// Longest window decoded in one pass; whisper's input is 30 s and the
+/// overlap with the previous chunk has to fit too.
+const MAX_CHUNK_MS: usize = 28_000;
+/// Chunks are not closed before this, even at a pause.
+const MIN_CHUNK_MS: usize = 10_000;
+/// Audio before a chunk boundary that the next chunk decodes again as context.
+const CHUNK_OVERLAP_MS: usize = 1_000;
+/// Quiet stretch looked for when choosing where to close a chunk.
+const PAUSE_MS: usize = 160;
+const ENERGY_FRAME_MS: usize = 20;
+/// Undecoded audio kept when decoding falls behind; older audio is dropped.
+const MAX_BUFFERED_MS: usize = 120_000;
+
+/// Incremental decoder state for one dictation session.
+///
+/// Words are committed once two consecutive hypotheses agree on them
+/// (LocalAgreement-2). Committed audio is trimmed from the decode window so
+/// each decode only covers the still-uncertain tail of the session.
+#[derive(Debug, Default)]
+pub struct StreamingDecoder {
+    window: Vec<i16>,
+    window_start_samples: usize,
+    committed: Vec<TimedWord>,
+    tentative: Vec<TimedWord>,
+    /// Words centred before this were committed by the previous chunk.
+    chunk_boundary_ms: u64,
+    dropped_samples: usize,
+    /// Text of an earlier session this one continues, prepended to the prompt.
+    context: String,
+}
+
+impl StreamingDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Conditions decodes on `text` from before this session, until enough
+    /// of the session is committed to replace it in the prompt.
+    pub fn set_context(&mut self, text: &str) {
+        self.context = text.trim().to_string();
+    }
+
+    /// Appends audio, dropping the oldest undecoded audio once more than
+    /// `MAX_BUFFERED_MS` is waiting so a stalled backend cannot grow memory
+    /// without bound.
+    pub fn push_audio(&mut self, samples: &[i16]) {
+        self.window.extend_from_slice(samples);
+        let excess = self
+            .window
+            .len()
+            .saturating_sub(MAX_BUFFERED_MS * SAMPLES_PER_MS);
+        if excess > 0 {
+            self.window.drain(..excess);
+            self.window_start_samples += excess;
+            self.dropped_samples += excess;
+            self.tentative.clear();
+        }
+    }
+
+    /// Milliseconds of audio dropped by the buffer cap this session.
+    pub fn dropped_ms(&self) -> u64 {
+        (self.dropped_samples / SAMPLES_PER_MS) as u64
+    }
+
+    /// Where to close the current chunk once the window is longer than one
+    /// decode can cover: the middle of the quietest pause between
+    /// `MIN_CHUNK_MS` and `MAX_CHUNK_MS`, as a sample offset into `window()`.
+    pub fn chunk_end(&self) -> Option<usize> {
+        if self.window.len() <= MAX_CHUNK_MS * SAMPLES_PER_MS {
+            return None;
+        }
+
+        let frame = ENERGY_FRAME_MS * SAMPLES_PER_MS;
+        let energies = self.window[..MAX_CHUNK_MS * SAMPLES_PER_MS]
+            .chunks(frame)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|sample| (*sample as i64).abs())
+                    .sum::<i64>()
+            })
+            .collect::<Vec<_>>();
+        let pause_frames = PAUSE_MS / ENERGY_FRAME_MS;
+        let first = MIN_CHUNK_MS / ENERGY_FRAME_MS;
+
+        // Later pauses win ties so chunks stay as long as possible.
+        let mut best = (i64::MAX, energies.len() - pause_frames);
+        for start in first..=energies.len() - pause_frames {
+            let energy = energies[start..start + pause_frames].iter().sum::<i64>();
+            if energy <= best.0 {
+                best = (energy, start);
+            }
+        }
+        Some((best.1 * ENERGY_FRAME_MS + PAUSE_MS / 2) * SAMPLES_PER_MS)
+    }
+
+    /// Commits a decode of `window()[..end]` as final. The next chunk starts
+    /// `CHUNK_OVERLAP_MS` before `end` so it decodes with the preceding audio
+    /// as context. The two decodes split the overlap at its middle: words
+    /// centred before it come from this chunk, the rest from the next, so a
+    /// word cut off at `end` is taken whole from the next chunk.
+    pub fn commit_chunk(&mut self, words: &[TimedWord], end: usize) {
+        let end = end.min(self.window.len());
+        let end_ms = ((self.window_start_samples + end) / SAMPLES_PER_MS) as u64;
+        let boundary_ms = end_ms.saturating_sub((CHUNK_OVERLAP_MS / 2) as u64);
+        let current = self.without_committed_overlap(self.to_session_time(words));
+        self.committed.extend(
+            current
+                .into_iter()
+                .filter(|word| word.start_ms + word.end_ms < 2 * boundary_ms),
+        );
+        self.tentative.clear();
+        self.chunk_boundary_ms = boundary_ms;
+
+        let cut = end.saturating_sub(CHUNK_OVERLAP_MS * SAMPLES_PER_MS);
+        self.window.drain(..cut);
+        self.window_start_samples += cut;
+    }
+
+    /// Audio that still needs decoding, starting at the first uncommitted word.
+    pub fn window(&self) -> &[i16] {
+        &self.window
+    }
+
+    /// Committed words with times relative to the start of the session.
+    pub fn words(&self) -> &[TimedWord] {
+        &self.committed
+    }
+
+    pub fn committed_text(&self) -> String {
+        join_words(&self.committed)
+    }
+
+    /// Committed text followed by the current tentative hypothesis.
+    pub fn text(&self) -> String {
+        join_words(self.committed.iter().chain(self.tentative.iter()))
+    }
+
+    /// Tail of the context and committed text to condition the next decode on.
+    pub fn prompt(&self) -> Option<String> {
+        let text = match (self.context.is_empty(), self.committed.is_empty()) {
+            (true, _) => self.committed_text(),
+            (false, true) => self.context.clone(),
+            (false, false) => format!("{} {}", self.context, self.committed_text()),
+        };
+        if text.is_empty() {
+            return None;
+        }
+        if text.len() <= PROMPT_CHARS {
+            return Some(text);
+        }
+
+        let mut start = text.len() - PROMPT_CHARS;
+        while !text.is_char_boundary(start) {
+            start += 1;
+        }
+        let tail = &text[start..];
+        let tail = tail.split_once(' ').map(|(_, rest)| rest).unwrap_or(tail);
+        Some(tail.to_string())
+    }
+
+    /// Folds a hypothesis for the current window (times relative to the
+    /// window start) into the stream, committing the words it shares with the
+    /// previous hypothesis.
+    pub fn apply_hypothesis(&mut self, words: &[TimedWord]) {
+        let current = self.without_committed_overlap(self.to_session_time(words));
+        let agreed = current
+            .iter()
+            .zip(self.tentative.iter())
+            .take_while(|(a, b)| same_word(a, b))
+            .count();
+
+        if agreed > 0 {
+            self.committed.extend_from_slice(&current[..agreed]);
+            self.trim_window(current.get(agreed).map(|word| word.start_ms));
+        }
+        self.tentative = current[agreed..].to_vec();
+    }
+
+    /// Replaces the tentative words without committing any, for hypotheses a
+    /// more accurate decode will redo.
+    pub fn set_tentative(&mut self, words: &[TimedWord]) {
+        self.tentative = self.without_committed_overlap(self.to_session_time(words));
+    }
+
+    /// Commits the final hypothesis for the remaining window and returns the
+    /// full session text.
+    pub fn finish(&mut self, words: &[TimedWord]) -> String {
+        let current = self.without_committed_overlap(self.to_session_time(words));
+        self.committed.extend(current);
+        self.tentative.clear();
+        self.window_start_samples += self.window.len();
+        self.window.clear();
+        self.committed_text()
+    }
+
+    fn window_start_ms(&self) -> u64 {
+        (self.window_start_samples / SAMPLES_PER_MS) as u64
+    }
+
+    fn to_session_time(&self, words: &[TimedWord]) -> Vec<TimedWord> {
+        let offset = self.window_start_ms();
+        words
+            .iter()
+            .map(|word| TimedWord {
+                start_ms: word.start_ms + offset,
+                end_ms: word.end_ms + offset,
+                ..word.clone()
+            })
+            .collect()
+    }
+
+    /// Drops words at the start of a hypothesis that repeat the committed tail,
+    /// which whisper tends to do when the prompt and window overlap.
+    fn without_committed_overlap(&self, mut current: Vec<TimedWord>) -> Vec<TimedWord> {
+        if self.window_start_ms() < self.chunk_boundary_ms {
+            let boundary_ms = self.chunk_boundary_ms;
+            current.retain(|word| word.start_ms + word.end_ms >= 2 * boundary_ms);
+        }
+
+        let near_window_start = current
+            .first()
+            .map(|word| word.start_ms < self.window_start_ms() + 1_000)
+            .unwrap_or(false);
+        if !near_window_start {
+            return current;
+        }
+
+        let max = MAX_OVERLAP_WORDS
+            .min(self.committed.len())
+            .min(current.len());
+        for len in (1..=max).rev() {
+            let tail = &self.committed[self.committed.len() - len..];
+            if tail
+                .iter()
+                .zip(current.iter())
+                .all(|(a, b)| same_word(a, b))
+            {
+                current.drain(..len);
+                break;
+            }
+        }
+        current
+    }
+
+    fn trim_window(&mut self, next_start_ms: Option<u64>) {
+        let Some(last) = self.committed.last() else {
+            return;
+        };
+
+        let cut_ms = match next_start_ms {
+            Some(next) if next >= last.end_ms => last.end_ms + (next - last.end_ms) / 2,
+            Some(next) => next,
+            None => last.end_ms,
+        };
+        let window_start_ms = self.window_start_ms();
+        if cut_ms <= window_start_ms {
+            return;
+        }
+
+        let cut = (((cut_ms - window_start_ms) as usize) * SAMPLES_PER_MS).min(self.window.len());
+        self.window.drain(..cut);
+        self.window_start_samples += cut;
+    }
+}
+
+/// Splits `text` into words spread over `start_ms..end_ms` by length, for
+/// backends that only report segment-level timing.
+pub fn words_from_text(text: &str, start_ms: u64, end_ms: u64) -> Vec<TimedWord> {
+    let pieces = text.split_whitespace().collect::<Vec<_>>();
+    let total_chars = pieces
+        .iter()
+        .map(|piece| piece.chars().count())
+        .sum::<usize>();
+    if total_chars == 0 {
+        return Vec::new();
+    }
+
+    let span = end_ms.saturating_sub(start_ms);
+    let mut consumed = 0usize;
+    pieces
+        .into_iter()
+        .map(|piece| {
+            let word_start = start_ms + span * consumed as u64 / total_chars as u64;
+            consumed += piece.chars().count();
+            let word_end = start_ms + span * consumed as u64 / total_chars as u64;
+            TimedWord {
+                text: piece.to_string(),
+                start_ms: word_start,
+                end_ms: word_end,
+                probability: 1.0,
+            }
+        })
+        .collect()
+}
+
+fn join_words<'a>(words: impl IntoIterator<Item = &'a TimedWord>) -> String {
+    let mut out = String::new();
+    for word in words {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&word.text);
+    }
+    out
+}
+
+fn same_word(a: &TimedWord, b: &TimedWord) -> bool {
+    let normalize = |text: &str| {
+        text.chars()
+            .filter(|ch| ch.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    normalize(&a.text) == normalize(&b.text)
+}
+
+/// Deterministic stand-in for whisper used by tests: each synthetic "word" is
+/// a burst of constant-valued samples, decoded back by value.
+#[cfg(test)]
+pub(crate) mod synthetic {
+    use anyhow::Result;
+
+    use super::SAMPLES_PER_MS;
+    use crate::core::transcriber::{DecodeOptions, TimedWord, Transcription, TranscriptionBackend};
+
+    const WORD_MS: usize = 400;
+    const GAP_MS: usize = 200;
+    const BASE_VALUE: i16 = 4_000;
+
+    pub fn word_text(index: usize) -> String {
+        format!("w{index}")
+    }
+
+    /// `count` words of speech, each followed by a short pause.
+    pub fn speech(count: usize) -> Vec<i16> {
+        words_with_gap(count, GAP_MS)
+    }
+
+    /// `count` words run together with no pause to cut at.
+    pub fn speech_without_pauses(count: usize) -> Vec<i16> {
+        words_with_gap(count, 0)
+    }
+
+    fn words_with_gap(count: usize, gap_ms: usize) -> Vec<i16> {
+        let mut samples = Vec::with_capacity(count * (WORD_MS + gap_ms) * SAMPLES_PER_MS);
+        for index in 0..count {
+            let value = BASE_VALUE + index as i16;
+            samples.extend(std::iter::repeat(value).take(WORD_MS * SAMPLES_PER_MS));
+            samples.extend(std::iter::repeat(0).take(gap_ms * SAMPLES_PER_MS));
+        }
+        samples
+    }
+
+    #[derive(Default)]
+    pub struct SyntheticBackend {
+        pub decoded_samples: usize,
+        pub decodes: usize,
+        pub longest_decode: usize,
+    }
+
+    impl TranscriptionBackend for SyntheticBackend {
+        fn transcribe(
+            &mut self,
+            samples_i16: &[i16],
+            _options: &DecodeOptions,
+        ) -> Result<Transcription> {
+            // Like whisper, a single pass cannot cover more than 30 s.
+            anyhow::ensure!(
+                samples_i16.len() <= 30_000 * SAMPLES_PER_MS,
+                "decode window longer than 30 s"
+            );
+            self.decoded_samples += samples_i16.len();
+            self.decodes += 1;
+            self.longest_decode = self.longest_decode.max(samples_i16.len());
+
+            let mut words = Vec::<TimedWord>::new();
+            let mut run_start = None::<usize>;
+            for (idx, sample) in samples_i16.iter().copied().chain([0]).enumerate() {
+                match (run_start, sample) {
+                    (None, value) if value >= BASE_VALUE => run_start = Some(idx),
+                    (Some(start), value) if value != samples_i16[start] => {
+                        words.push(TimedWord {
+                            text: word_text((samples_i16[start] - BASE_VALUE) as usize),
+                            start_ms: (start / SAMPLES_PER_MS) as u64,
+                            end_ms: (idx / SAMPLES_PER_MS) as u64,
+                            probability: 1.0,
+                        });
+                        run_start = (value >= BASE_VALUE).then_some(idx);
+                    }
+                    _ => {}
+                }
+            }
+
+            let text = words
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            Ok(Transcription {
+                text,
+                language: Some("en".to_string()),
+                words,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{synthetic, words_from_text, StreamingDecoder};
+    use crate::core::transcriber::{DecodeOptions, TimedWord, TranscriptionBackend};
+
+    fn word(text: &str, start_ms: u64, end_ms: u64) -> TimedWord {
+        TimedWord {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            probability: 1.0,
+        }
+    }
+
+    #[test]
+    fn commits_words_confirmed_by_consecutive_hypotheses() {
+        let mut stream = StreamingDecoder::new();
+        stream.push_audio(&vec![0; 16 * 3_000]);
+
+        stream.apply_hypothesis(&[word("Their", 0, 400), word("cat", 500, 900)]);
+        assert_eq!(stream.committed_text(), "");
+        assert_eq!(stream.text(), "Their cat");
+
+        stream.apply_hypothesis(&[
+            word("There", 0, 400),
+            word("cat", 500, 900),
+            word("sat", 1_000, 1_400),
+        ]);
+        assert_eq!(stream.committed_text(), "");
+
+        stream.apply_hypothesis(&[
+            word("There", 0, 400),
+            word("cat,", 500, 900),
+            word("sat", 1_000, 1_400),
+            word("down", 1_500, 1_900),
+        ]);
+        assert_eq!(stream.committed_text(), "There cat, sat");
+        assert_eq!(stream.text(), "There cat, sat down");
+    }
+
+    #[test]
+    fn trims_committed_audio_from_the_window() {
+        let mut stream = StreamingDecoder::new();
+        stream.push_audio(&vec![0; 16 * 2_000]);
+
+        let hypothesis = [word("hello", 0, 400), word("world", 600, 1_000)];
+        stream.apply_hypothesis(&hypothesis);
+        stream.apply_hypothesis(&hypothesis);
+
+        assert_eq!(stream.committed_text(), "hello world");
+        assert_eq!(stream.window().len(), 16 * 1_000);
+
+        stream.push_audio(&vec![0; 16 * 1_000]);
+        stream.apply_hypothesis(&[word("again", 100, 500)]);
+        assert_eq!(stream.text(), "hello world again");
+        assert_eq!(
+            stream.finish(&[word("again", 100, 500)]),
+            "hello world again"
+        );
+    }
+
+    #[test]
+    fn drops_repeated_committed_words_at_window_start() {
+        let mut stream = StreamingDecoder::new();
+        stream.push_audio(&vec![0; 16 * 2_000]);
+        let first = [
+            word("one", 0, 300),
+            word("two", 400, 700),
+            word("three", 800, 1_100),
+        ];
+        stream.apply_hypothesis(&first);
+        stream.apply_hypothesis(&first[..2]);
+        assert_eq!(stream.committed_text(), "one two");
+
+        let final_text = stream.finish(&[word("two", 0, 200), word("three", 300, 600)]);
+        assert_eq!(final_text, "one two three");
+    }
+
+    #[test]
+    fn prompt_is_committed_tail_on_word_boundary() {
+        let mut stream = StreamingDecoder::new();
+        let words = (0..80)
+            .map(|idx| word(&format!("word{idx}"), idx * 100, idx * 100 + 80))
+            .collect::<Vec<_>>();
+        stream.finish(&words);
+
+        let prompt = stream.prompt().unwrap();
+        assert!(prompt.len() <= 200);
+        assert!(prompt.starts_with("word"));
+        assert!(prompt.ends_with("word79"));
+    }
+
+    #[test]
+    fn prompt_continues_from_the_previous_session() {
+        let mut stream = StreamingDecoder::new();
+        stream.set_context("The deploy finished.");
+        assert_eq!(stream.prompt().as_deref(), Some("The deploy finished."));
+
+        stream.finish(&[word("Rollback", 0, 300), word("next.", 300, 600)]);
+        assert_eq!(
+            stream.prompt().as_deref(),
+            Some("The deploy finished. Rollback next.")
+        );
+
+        stream.reset();
+        assert_eq!(stream.prompt(), None);
+    }
+
+    #[test]
+    fn words_from_text_spreads_time_by_length() {
+        let words = words_from_text(" hi there", 1_000, 1_700);
+        assert_eq!(
+            words,
+            vec![word("hi", 1_000, 1_200), word("there", 1_200, 1_700)]
+        );
+    }
+
+    /// Decodes `audio` with chunking alone, as when partial decodes never
+    /// reach agreement, and returns the session text.
+    fn decode_in_chunks(audio: &[i16], backend: &mut synthetic::SyntheticBackend) -> String {
+        let options = DecodeOptions::default();
+        let mut stream = StreamingDecoder::new();
+        for piece in audio.chunks(16 * 350) {
+            stream.push_audio(piece);
+            while let Some(end) = stream.chunk_end() {
+                let result = backend
+                    .transcribe(&stream.window()[..end], &options)
+                    .unwrap();
+                stream.commit_chunk(&result.words, end);
+            }
+        }
+        let result = backend.transcribe(stream.window(), &options).unwrap();
+        stream.finish(&result.words)
+    }
+
+    fn expected_text(count: usize) -> String {
+        (0..count)
+            .map(synthetic::word_text)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn long_session_is_chunked_at_pauses_without_duplicates() {
+        let audio = synthetic::speech(400); // four minutes
+        let mut backend = synthetic::SyntheticBackend::default();
+
+        assert_eq!(decode_in_chunks(&audio, &mut backend), expected_text(400));
+        assert!(backend.decodes >= 9, "only {} decodes", backend.decodes);
+        assert!(backend.longest_decode <= 16 * 28_000);
+    }
+
+    #[test]
+    fn chunks_without_pauses_stitch_words_cut_at_the_boundary() {
+        let audio = synthetic::speech_without_pauses(500); // three minutes, twenty seconds
+        let mut backend = synthetic::SyntheticBackend::default();
+
+        assert_eq!(decode_in_chunks(&audio, &mut backend), expected_text(500));
+    }
+
+    #[test]
+    fn chunk_end_falls_in_the_latest_pause() {
+        let mut stream = StreamingDecoder::new();
+        stream.push_audio(&synthetic::speech(45));
+        assert_eq!(stream.chunk_end(), None);
+
+        stream.push_audio(&synthetic::speech(15));
+        let end = stream.chunk_end().unwrap();
+        assert!((16 * 27_000..=16 * 28_000).contains(&end), "{end}");
+        assert_eq!(stream.window()[end], 0);
+    }
+
+    #[test]
+    fn undecoded_audio_is_capped() {
+        let mut stream = StreamingDecoder::new();
+        for _ in 0..180 {
+            stream.push_audio(&vec![0; 16_000]);
+        }
+        assert_eq!(stream.window().len(), 16 * 120_000);
+        assert_eq!(stream.dropped_ms(), 60_000);
+    }
+
+    #[test]
+    fn long_session_decode_cost_stays_linear() {
+        const CADENCE_SAMPLES: usize = 16 * 350;
+        let audio = synthetic::speech(200); // two minutes
+        let options = DecodeOptions::default();
+
+        let mut streaming = synthetic::SyntheticBackend::default();
+        let mut stream = StreamingDecoder::new();
+        // Re-decoding the whole session costs its full length on every pass.
+        let mut session_len = 0usize;
+        let mut full_decoded_samples = 0usize;
+
+        for chunk in audio.chunks(CADENCE_SAMPLES) {
+            stream.push_audio(chunk);
+            let result = streaming.transcribe(stream.window(), &options).unwrap();
+            stream.apply_hypothesis(&result.words);
+
+            session_len += chunk.len();
+            full_decoded_samples += session_len;
+        }
+        let result = streaming.transcribe(stream.window(), &options).unwrap();
+        let text = stream.finish(&result.words);
+
+        let expected = (0..200)
+            .map(synthetic::word_text)
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(text, expected);
+
+        let streaming_rtf = streaming.decoded_samples as f64 / audio.len() as f64;
+        let full_rtf = full_decoded_samples as f64 / audio.len() as f64;
+        assert!(
+            streaming_rtf < 10.0,
+            "streaming decoded {streaming_rtf:.1}x the audio"
+        );
+        assert!(
+            full_rtf > 100.0,
+            "full re-decode decoded {full_rtf:.1}x the audio"
+        );
+    }
+}
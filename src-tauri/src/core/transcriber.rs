@@ -1,131 +1,1197 @@
-use std::{path::PathBuf, time::{Duration, Instant}};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use tokio::sync::mpsc;
 use whisper_rs::{
     convert_integer_to_float_audio, FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters,
+    WhisperState,
 };
 
 use super::{
-    state::EngineCommand,
-    vad::resample_mono_to_16k,
+    cadence::DecodeCadence,
+    command_mode::CommandSet,
+    context::{self, SessionContext},
+    filter::{self, HallucinationFilter},
+    health::{Component, Health},
+    http_backend::HttpTranscriber,
+    models,
+    postprocess,
+    snippets::{LocalTime, Snippet, SnippetTable},
+    state::{DictationMode, EngineCommand, TranscriptEdit},
+    streaming::StreamingDecoder,
+    vad::{resample_mono_to_16k, SpeechDetector},
+    vocabulary::{self, VocabularyCorrector},
+    voice_commands::{self, EditedTranscript},
     AudioFrame,
+    DecodingProfile,
+    EngineSettings,
+    HttpBackendSettings,
+    ModelLoadPolicy,
+    TranscriptionBackendKind,
     TranscriptionModel,
 };
 
+/// Audio used for whisper's language identification at the start of a session.
+const LANGUAGE_DETECT_SAMPLES: usize = 16_000 * 3;
+/// How often an idle transcriber checks whether to unload its model.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub enum TranscriberMessage {
-    Begin,
+    Begin(DictationMode),
     Audio(AudioFrame),
     End,
     Cancel,
-    UpdateModel(TranscriptionModel),
+    /// The final model and, for cascade mode, a faster model for partials.
+    UpdateModel(TranscriptionModel, Option<TranscriptionModel>),
+    UpdateBackend(TranscriptionBackendKind, HttpBackendSettings),
+    UpdateLanguage(String),
+    UpdateSensitivity(f32),
+    UpdateVocabulary(Vec<String>, bool),
+    UpdateBlocklist(Vec<String>),
+    UpdateDecoding(DecodingProfile, DecodingProfile),
+    UpdateThreads(u32),
+    UpdateLoadPolicy(ModelLoadPolicy, u32),
+    UpdateContextCarry(bool, u32),
+    UpdateSpokenPunctuation(bool),
+    UpdateVoiceCommands(bool),
+    UpdateSnippets(Vec<Snippet>),
+    UpdateCommands(CommandSet),
+    /// The last dictation was undone, so the next one does not continue it.
+    ClearContext,
+    /// Sent by the dispatcher when no message arrived for a while.
+    IdleCheck,
+    /// A background model or backend load finished.
+    BackendLoaded(LoadedBackend),
+}
+
+/// Result of a background load, tagged with the load it answers so a result
+/// overtaken by a newer settings change is discarded.
+pub struct LoadedBackend {
+    generation: u64,
+    label: String,
+    result: Result<Box<dyn TranscriptionBackend>, String>,
+}
+
+impl std::fmt::Debug for LoadedBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadedBackend")
+            .field("generation", &self.generation)
+            .field("label", &self.label)
+            .field("ok", &self.result.is_ok())
+            .finish()
+    }
+}
+
+/// Progress of switching to a newly selected model or backend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ModelLoadStatus {
+    Loading { model: String },
+    /// Loaded, and waiting for the current dictation to end before it is used.
+    Ready { model: String },
+    Active { model: String },
+    /// The load failed; the previous model stays in use.
+    Failed { model: String, error: String },
+}
+
+/// Settings the transcriber worker needs to open and drive a backend.
+#[derive(Debug, Clone)]
+pub struct TranscriberConfig {
+    pub model_root: PathBuf,
+    pub model: TranscriptionModel,
+    /// Faster model for partials in cascade mode.
+    pub partial_model: Option<TranscriptionModel>,
+    pub backend: TranscriptionBackendKind,
+    pub http: HttpBackendSettings,
+    pub language: String,
+    /// Speech sensitivity shared with the VAD; silent stretches skip partials.
+    pub sensitivity: f32,
+    pub vocabulary: Vec<String>,
+    /// Rewrite near-miss spellings of vocabulary terms after decoding.
+    pub correct_vocabulary: bool,
+    /// Artifact phrases dropped when they are all a decode produced.
+    pub blocklist: Vec<String>,
+    pub partial_decoding: DecodingProfile,
+    pub final_decoding: DecodingProfile,
+    /// Inference threads; 0 picks a count from the available cores.
+    pub threads: u32,
+    pub load_policy: ModelLoadPolicy,
+    pub idle_unload_minutes: u32,
+    /// Condition a session on the previous one when it follows in the same app.
+    pub carry_context: bool,
+    pub context_window_seconds: u32,
+    pub spoken_punctuation: bool,
+    pub voice_commands: bool,
+    pub snippets: SnippetTable,
+    /// Command-mode grammar; loaded from its own file, not the settings.
+    pub commands: CommandSet,
+    /// Opens the configured backend; tests substitute slow or synthetic ones.
+    pub opener: BackendOpener,
+}
+
+pub type BackendOpener =
+    fn(&TranscriberConfig, &CancelToken) -> Result<Box<dyn TranscriptionBackend>>;
+
+impl TranscriberConfig {
+    pub fn from_settings(settings: &EngineSettings, model_root: PathBuf) -> Self {
+        Self {
+            model_root,
+            model: settings.model.clone(),
+            partial_model: settings.partial_model.clone(),
+            backend: settings.backend,
+            http: settings.http_backend.clone(),
+            language: settings.language.clone(),
+            sensitivity: settings.sensitivity,
+            vocabulary: settings.vocabulary.clone(),
+            correct_vocabulary: settings.correct_vocabulary,
+            blocklist: settings.hallucination_blocklist.clone(),
+            partial_decoding: settings.partial_decoding,
+            final_decoding: settings.final_decoding,
+            threads: settings.decode_threads,
+            load_policy: settings.model_load_policy,
+            idle_unload_minutes: settings.model_idle_minutes,
+            carry_context: settings.carry_context,
+            context_window_seconds: settings.context_window_seconds,
+            spoken_punctuation: settings.spoken_punctuation,
+            voice_commands: settings.voice_commands,
+            snippets: SnippetTable::new(&settings.snippets),
+            commands: CommandSet::default(),
+            opener: open_configured_backend,
+        }
+    }
+
+    /// Health of a backend that is not loaded yet: a missing or damaged local
+    /// model is reported now rather than at the first dictation.
+    fn preflight(&self) -> Health {
+        if self.backend != TranscriptionBackendKind::Local {
+            return Health::Ok;
+        }
+        let checked = self
+            .cascade_model()
+            .into_iter()
+            .chain([&self.model])
+            .try_for_each(|model| models::check_model(&self.model_root, model));
+        match checked {
+            Ok(()) => Health::Ok,
+            Err(error) => Health::Disabled(error.to_string()),
+        }
+    }
+
+    /// The partials model when cascade mode applies: a local backend with a
+    /// partials model different from the final one.
+    fn cascade_model(&self) -> Option<&TranscriptionModel> {
+        let local = self.backend == TranscriptionBackendKind::Local;
+        self.partial_model
+            .as_ref()
+            .filter(|partial| local && **partial != self.model)
+    }
+
+    /// The model or server a load of this config refers to, for status events.
+    fn backend_label(&self) -> String {
+        match self.backend {
+            TranscriptionBackendKind::Local => match self.cascade_model() {
+                Some(partial) => format!("{} + {}", partial.id(), self.model.id()),
+                None => self.model.id().to_string(),
+            },
+            TranscriptionBackendKind::Http => self.http.endpoint.clone(),
+        }
+    }
+
+    /// Whether a model idle for `idle` should be dropped to free its memory.
+    fn unload_due(&self, idle: Duration) -> bool {
+        let limit = Duration::from_secs(u64::from(self.idle_unload_minutes.max(1)) * 60);
+        self.load_policy == ModelLoadPolicy::UnloadWhenIdle && idle >= limit
+    }
+
+    /// The configured decode language, or `None` when it should be detected.
+    fn fixed_language(&self) -> Option<&str> {
+        match self.language.trim() {
+            "" | "auto" => None,
+            code => Some(code),
+        }
+    }
+
+    fn corrector(&self) -> VocabularyCorrector {
+        if self.correct_vocabulary {
+            VocabularyCorrector::new(&self.vocabulary)
+        } else {
+            VocabularyCorrector::default()
+        }
+    }
+
+    fn filter(&self) -> HallucinationFilter {
+        HallucinationFilter::new(&self.blocklist)
+    }
+
+    /// Text as typed: vocabulary fixes, editing commands, spoken punctuation,
+    /// capitalization and, for the final text, a closing period, then snippets.
+    fn typed_text(
+        &self,
+        corrector: &VocabularyCorrector,
+        raw: &str,
+        finalize: bool,
+    ) -> EditedTranscript {
+        let corrected = corrector.correct(raw);
+        let mut edited = if self.voice_commands {
+            voice_commands::apply_voice_commands(&corrected)
+        } else {
+            EditedTranscript {
+                text: corrected,
+                ..EditedTranscript::default()
+            }
+        };
+        if self.spoken_punctuation {
+            edited.text = postprocess::apply_spoken_punctuation(&edited.text);
+        }
+        edited.text = normalize_transcript(&edited.text, finalize);
+        edited.text = self.snippets.expand(&edited.text, LocalTime::now());
+        edited
+    }
+
+    fn open_backend(&self, cancel: &CancelToken) -> Result<Box<dyn TranscriptionBackend>> {
+        (self.opener)(self, cancel)
+    }
+}
+
+fn open_configured_backend(
+    config: &TranscriberConfig,
+    cancel: &CancelToken,
+) -> Result<Box<dyn TranscriptionBackend>> {
+    let open_local = |model: &TranscriptionModel| {
+        TranscriberRuntime::new(
+            &config.model_root,
+            model,
+            decode_thread_count(config.threads),
+            cancel.clone(),
+        )
+    };
+    match config.backend {
+        TranscriptionBackendKind::Local => match config.cascade_model() {
+            Some(partial) => Ok(Box::new(CascadeBackend {
+                fast: Box::new(open_local(partial)?),
+                accurate: Box::new(open_local(&config.model)?),
+            })),
+            None => Ok(Box::new(open_local(&config.model)?)),
+        },
+        TranscriptionBackendKind::Http => {
+            Ok(Box::new(HttpTranscriber::new(config.http.clone())?))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeOptions {
+    /// Language code to decode as; `None` lets the backend detect it.
+    pub language: Option<String>,
+    /// Emit an English translation instead of a same-language transcript.
+    pub translate: bool,
+    /// Previously committed text the decode should continue from.
+    pub prompt: Option<String>,
+    pub decoding: DecodingProfile,
+    /// The decode produces final text rather than a partial.
+    pub final_pass: bool,
+}
+
+/// A decoded word with its position in the decoded audio.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TimedWord {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Decoder confidence in 0..=1; 1 when the backend does not report it.
+    pub probability: f32,
+}
+
+/// A run of words between sentence ends or long pauses.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TranscriptSegment {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Mean probability of the segment's words.
+    pub probability: f32,
+}
+
+/// The finished text of a session together with its timing and confidence.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FinalTranscript {
+    /// Text as typed into the focused app.
+    pub text: String,
+    pub language: Option<String>,
+    /// Words with times relative to the start of the session.
+    pub words: Vec<TimedWord>,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcription {
+    pub text: String,
+    pub language: Option<String>,
+    pub words: Vec<TimedWord>,
+}
+
+/// A speech-to-text engine that turns 16 kHz mono PCM into raw transcript text.
+pub trait TranscriptionBackend: Send {
+    fn transcribe(&mut self, samples_i16: &[i16], options: &DecodeOptions)
+        -> Result<Transcription>;
+
+    /// Identifies the spoken language from the start of the audio, if supported.
+    fn detect_language(&mut self, _samples_i16: &[i16]) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Whether the loaded model can decode languages other than English.
+    fn multilingual(&self) -> bool {
+        true
+    }
+
+    /// Number of prompt tokens `text` takes up for this backend's model.
+    fn count_prompt_tokens(&self, text: &str) -> usize {
+        vocabulary::estimate_tokens(text)
+    }
+
+    /// Sets how many CPU threads inference may use, where that applies.
+    fn set_threads(&mut self, _threads: usize) {}
+
+    /// Whether partials come from a less accurate model than final passes,
+    /// so they should be shown but never committed.
+    fn cascaded(&self) -> bool {
+        false
+    }
+}
+
+/// Resolves the thread setting: an explicit count is capped at the available
+/// cores; 0 leaves one core for audio capture and the UI, up to 8 threads,
+/// past which whisper.cpp stops getting faster.
+pub fn decode_thread_count(setting: u32) -> usize {
+    let available = std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(4);
+    if setting > 0 {
+        (setting as usize).min(available)
+    } else {
+        available.saturating_sub(1).clamp(1, 8)
+    }
+}
+
+/// Rejects unknown language codes and non-English languages on `.en` models.
+pub fn validate_language(language: &str, multilingual: bool) -> Result<()> {
+    let code = language.trim();
+    if code.is_empty() || code == "auto" {
+        return Ok(());
+    }
+    if whisper_rs::get_lang_id(code).is_none() {
+        anyhow::bail!("unsupported transcription language {code:?}");
+    }
+    if !multilingual && code != "en" {
+        anyhow::bail!("the selected model only supports English, not {code:?}");
+    }
+    Ok(())
 }
 
 pub fn spawn_transcriber_worker(
     mut rx: mpsc::Receiver<TranscriberMessage>,
     command_tx: mpsc::Sender<EngineCommand>,
-    model_root: PathBuf,
-    initial_model: TranscriptionModel,
+    config: TranscriberConfig,
 ) {
+    let cancel = CancelToken::default();
+    let (inference_tx, inference_rx) = mpsc::channel::<TranscriberMessage>(256);
+
+    let inference_cancel = cancel.clone();
+    let health_tx = command_tx.clone();
+    let reload_tx = inference_tx.downgrade();
+    let spawned = std::thread::Builder::new()
+        .name("lumitype-transcriber".to_string())
+        .spawn(move || {
+            let mut backend = None;
+            if config.load_policy == ModelLoadPolicy::Always {
+                match config.open_backend(&inference_cancel) {
+                    Ok(opened) => {
+                        backend = Some(opened);
+                        report_health(&command_tx, Health::Ok);
+                    }
+                    Err(error) => {
+                        eprintln!("transcriber model failed to load: {error}");
+                        report_health(&command_tx, Health::Disabled(error.to_string()));
+                    }
+                }
+            } else {
+                report_health(&command_tx, config.preflight());
+            }
+            run_transcriber(
+                inference_rx,
+                reload_tx,
+                command_tx,
+                config,
+                backend,
+                inference_cancel,
+            );
+        });
+    if let Err(error) = spawned {
+        eprintln!("failed to start transcriber thread: {error}");
+        let _ = health_tx.try_send(EngineCommand::ReportHealth(
+            Component::Transcriber,
+            Health::Disabled(format!("failed to start transcriber thread: {error}")),
+        ));
+        return;
+    }
+
+    // Inference blocks for hundreds of milliseconds, so it runs on its own
+    // thread; this task only forwards messages and flags cancellation early
+    // enough to abort a decode that is already running.
     tauri::async_runtime::spawn(async move {
-        let mut runtime = match TranscriberRuntime::new(model_root.clone(), initial_model) {
-            Ok(runtime) => runtime,
-            Err(error) => {
-                eprintln!("transcriber disabled: {error}");
-                return;
+        loop {
+            let message = match tokio::time::timeout(IDLE_CHECK_INTERVAL, rx.recv()).await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(_) => TranscriberMessage::IdleCheck,
+            };
+            if matches!(message, TranscriberMessage::Cancel) {
+                cancel.request();
             }
-        };
+            if inference_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+    });
+}
 
-        let mut session_audio = Vec::<i16>::new();
-        let mut last_emitted = String::new();
-        let mut last_decode_at = Instant::now();
+/// Cancellation shared between the message dispatcher and the inference
+/// thread. Every `Cancel` bumps `requested` as soon as it is received; the
+/// inference thread acknowledges it once it reaches that message in order.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    counters: Arc<CancelCounters>,
+}
 
-        while let Some(message) = rx.recv().await {
-            match message {
-                TranscriberMessage::Begin => {
-                    session_audio.clear();
-                    last_emitted.clear();
-                    last_decode_at = Instant::now();
+#[derive(Debug, Default)]
+struct CancelCounters {
+    requested: AtomicU64,
+    acknowledged: AtomicU64,
+}
+
+impl CancelToken {
+    pub fn request(&self) {
+        self.counters.requested.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn acknowledge(&self) {
+        self.counters.acknowledged.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether a `Cancel` is queued that the inference thread has not handled yet.
+    pub fn is_cancelled(&self) -> bool {
+        self.counters.requested.load(Ordering::SeqCst)
+            > self.counters.acknowledged.load(Ordering::SeqCst)
+    }
+}
+
+/// `reload_tx` feeds background load results back into `rx`; it is weak so
+/// the worker still stops once the dispatcher drops its sender.
+fn run_transcriber(
+    mut rx: mpsc::Receiver<TranscriberMessage>,
+    reload_tx: mpsc::WeakSender<TranscriberMessage>,
+    command_tx: mpsc::Sender<EngineCommand>,
+    mut config: TranscriberConfig,
+    mut backend: Option<Box<dyn TranscriptionBackend>>,
+    cancel: CancelToken,
+) {
+    // A model loaded in the background while a session was running; it
+    // replaces `backend` once the session ends.
+    let mut pending: Option<(String, Box<dyn TranscriptionBackend>)> = None;
+    let mut load_generation = 0u64;
+    // A load started by a session without a model is still running.
+    let mut loading = false;
+    // Messages set aside while an ending session waited for its model.
+    let mut backlog = VecDeque::new();
+    let mut session_mode = DictationMode::default();
+    let mut in_session = false;
+    let mut last_used = Instant::now();
+    let mut corrector = config.corrector();
+    let mut filter = config.filter();
+    let mut stream = StreamingDecoder::new();
+    let mut language_probe = Vec::<i16>::new();
+    let mut last_emitted = String::new();
+    let mut cadence = DecodeCadence::new(Instant::now());
+    let mut speech = SpeechDetector::new(config.sensitivity);
+    let mut options = DecodeOptions::default();
+    let mut language_detection_pending = false;
+    let mut carried: Option<SessionContext> = None;
+    let mut command_session = false;
+
+    while let Some(message) = backlog.pop_front().or_else(|| rx.blocking_recv()) {
+        match message {
+            TranscriberMessage::Begin(mode) => {
+                stream.reset();
+                language_probe.clear();
+                last_emitted.clear();
+                cadence.begin(Instant::now());
+                speech.reset();
+                command_session = mode == DictationMode::Command;
+                session_mode = mode;
+                in_session = true;
+                last_used = Instant::now();
+
+                let window = Duration::from_secs(config.context_window_seconds.into());
+                let focus = context::focused_app();
+                carried = carried.filter(|previous| {
+                    config.carry_context && previous.continues(Instant::now(), focus, window)
+                });
+                if let Some(previous) = &carried {
+                    stream.set_context(previous.text());
                 }
-                TranscriberMessage::Audio(frame) => {
-                    session_audio.extend(resample_mono_to_16k(&frame.samples, frame.sample_rate));
-                    if last_decode_at.elapsed() < Duration::from_millis(350) {
-                        continue;
-                    }
-                    if session_audio.len() < 3200 {
-                        continue;
+
+                // Begin is sent the moment the wake word or hotkey fires, so a
+                // lazily loaded model starts loading before audio needs it. It
+                // loads on its own thread; audio is buffered in the stream
+                // meanwhile and decoded once the model arrives.
+                let Some(backend) = backend.as_deref() else {
+                    if !loading {
+                        loading = true;
+                        load_generation += 1;
+                        load_in_background(
+                            &config,
+                            &cancel,
+                            &reload_tx,
+                            &command_tx,
+                            load_generation,
+                        );
                     }
+                    continue;
+                };
+                language_detection_pending =
+                    configure_session(backend, mode, &config, &mut options, &command_tx);
+            }
+            TranscriberMessage::Audio(frame) => {
+                let samples = resample_mono_to_16k(&frame.samples, frame.sample_rate);
+                stream.push_audio(&samples);
+                cadence.note_audio(speech.contains_speech(&samples));
+                if language_detection_pending {
+                    let wanted = LANGUAGE_DETECT_SAMPLES.saturating_sub(language_probe.len());
+                    language_probe.extend_from_slice(&samples[..samples.len().min(wanted)]);
+                }
+                let Some(backend) = backend.as_deref_mut() else {
+                    continue;
+                };
+                if language_detection_pending && language_probe.len() >= LANGUAGE_DETECT_SAMPLES
+                {
+                    language_detection_pending = false;
+                    detect_session_language(
+                        backend,
+                        &language_probe,
+                        &mut options,
+                        &command_tx,
+                    );
+                }
+                if cancel.is_cancelled() {
+                    continue;
+                }
+                // Chunks are closed regardless of the partial cadence, so the
+                // window stays decodable even when partials fall behind.
+                let _ = commit_full_chunks(
+                    backend,
+                    &mut stream,
+                    &mut options,
+                    &config,
+                    &filter,
+                    &cancel,
+                );
+                if !cadence.decode_due(Instant::now()) {
+                    continue;
+                }
+                if stream.window().len() < 3200 {
+                    continue;
+                }
 
-                    if let Ok(text) = runtime.transcribe(&session_audio, false) {
-                        let delta = transcript_delta(&last_emitted, &text);
-                        if !delta.is_empty() {
+                options.prompt = vocabulary::build_prompt(
+                    &config.vocabulary,
+                    stream.prompt().as_deref(),
+                    &|text| backend.count_prompt_tokens(text),
+                );
+                options.decoding = config.partial_decoding;
+                options.final_pass = false;
+                let started = Instant::now();
+                let decoded = backend.transcribe(stream.window(), &options);
+                let audio = Duration::from_millis(stream.window().len() as u64 / 16);
+                cadence.record_decode(Instant::now(), started.elapsed(), audio);
+                if let Ok(result) = decoded {
+                    // Cascade partials are only a preview; chunk commits and
+                    // the final pass redo them on the accurate model.
+                    if backend.cascaded() {
+                        stream.set_tentative(&filter.filter(stream.words(), &result.words));
+                    } else {
+                        stream.apply_hypothesis(&filter.filter(stream.words(), &result.words));
+                    }
+                    // Commands run at the end of the session; a partial only
+                    // shows their effect on this session's text.
+                    let edited = config.typed_text(&corrector, &stream.text(), false);
+                    // A command is not typed; text typed before its trigger
+                    // phrase was recognized is taken back.
+                    let commanding =
+                        command_session || config.commands.strip_trigger(&edited.text).is_some();
+                    let text = if commanding { String::new() } else { edited.text };
+                    if !text.is_empty() || edited.applied || commanding {
+                        let edit = TranscriptEdit::between(&last_emitted, &text);
+                        if !edit.is_empty() {
                             let _ = command_tx
-                                .send(EngineCommand::TranscriptionDelta(delta.clone()))
-                                .await;
+                                .blocking_send(EngineCommand::TranscriptionEdit(edit));
                         }
                         last_emitted = text;
                     }
-                    last_decode_at = Instant::now();
                 }
-                TranscriberMessage::End => {
-                    if let Ok(text) = runtime.transcribe(&session_audio, true) {
-                        let delta = transcript_delta(&last_emitted, &text);
-                        if !delta.is_empty() {
+            }
+            TranscriberMessage::End => {
+                if backend.is_none() && loading {
+                    // Hold the end of the session until the model is ready so
+                    // the buffered audio is still transcribed.
+                    if let Some(loaded) = wait_for_load(&mut rx, &mut backlog, load_generation) {
+                        backlog.push_front(TranscriberMessage::End);
+                        backlog.push_front(TranscriberMessage::BackendLoaded(loaded));
+                        continue;
+                    }
+                }
+                in_session = false;
+                last_used = Instant::now();
+                let Some(backend) = backend.as_deref_mut() else {
+                    stream.reset();
+                    last_emitted.clear();
+                    let _ = command_tx.blocking_send(EngineCommand::TranscriptionFinished);
+                    continue;
+                };
+                if language_detection_pending && !language_probe.is_empty() {
+                    language_detection_pending = false;
+                    detect_session_language(
+                        backend,
+                        &language_probe,
+                        &mut options,
+                        &command_tx,
+                    );
+                }
+                let _ = commit_full_chunks(
+                    backend,
+                    &mut stream,
+                    &mut options,
+                    &config,
+                    &filter,
+                    &cancel,
+                );
+                if stream.dropped_ms() > 0 {
+                    let _ = command_tx.blocking_send(EngineCommand::ReportError(format!(
+                        "transcription fell behind; {:.0} s of audio was skipped",
+                        stream.dropped_ms() as f64 / 1_000.0
+                    )));
+                }
+                options.prompt = vocabulary::build_prompt(
+                    &config.vocabulary,
+                    stream.prompt().as_deref(),
+                    &|text| backend.count_prompt_tokens(text),
+                );
+                options.decoding = config.final_decoding;
+                options.final_pass = true;
+                let decoded = backend.transcribe(stream.window(), &options);
+                match &decoded {
+                    Ok(_) => report_health(&command_tx, Health::Ok),
+                    Err(_) if cancel.is_cancelled() => {}
+                    Err(error) => report_health(
+                        &command_tx,
+                        Health::Degraded(format!("last transcription failed: {error}")),
+                    ),
+                }
+                if let Ok(result) = decoded {
+                    if options.language.is_none() {
+                        if let Some(language) = result.language.clone() {
                             let _ = command_tx
-                                .send(EngineCommand::TranscriptionDelta(delta))
-                                .await;
+                                .blocking_send(EngineCommand::LanguageDetected(language));
                         }
                     }
-                    session_audio.clear();
-                    last_emitted.clear();
-                    let _ = command_tx.send(EngineCommand::TranscriptionFinished).await;
+                    let text = stream.finish(&filter.filter(stream.words(), &result.words));
+                    let spoken_command = if command_session {
+                        Some(text.clone())
+                    } else {
+                        config.commands.strip_trigger(&text)
+                    };
+                    let edited = match spoken_command {
+                        Some(_) => EditedTranscript {
+                            applied: true,
+                            ..EditedTranscript::default()
+                        },
+                        None => config.typed_text(&corrector, &text, true),
+                    };
+                    let text = edited.text;
+                    let edit = TranscriptEdit::between(&last_emitted, &text);
+                    if (!text.is_empty() || edited.applied) && !edit.is_empty() {
+                        let _ = command_tx.blocking_send(EngineCommand::TranscriptionEdit(edit));
+                    }
+                    for command in edited.commands {
+                        let _ = command_tx.blocking_send(EngineCommand::VoiceCommand(command));
+                    }
+                    if let Some(spoken) = spoken_command {
+                        run_command(&config.commands, &spoken, &command_tx);
+                    }
+                    if !text.is_empty() {
+                        if config.carry_context {
+                            carried = stream.prompt().map(|tail| {
+                                SessionContext::new(tail, Instant::now(), context::focused_app())
+                            });
+                        }
+                        let words = align_words(&text, stream.words());
+                        let transcript = FinalTranscript {
+                            segments: segments_from_words(&words),
+                            words,
+                            text,
+                            language: options.language.clone().or(result.language),
+                        };
+                        let _ = command_tx
+                            .blocking_send(EngineCommand::TranscriptFinalized(transcript));
+                    }
                 }
-                TranscriberMessage::Cancel => {
-                    session_audio.clear();
-                    last_emitted.clear();
-                    let _ = command_tx.send(EngineCommand::TranscriptionFinished).await;
+                stream.reset();
+                last_emitted.clear();
+                let stats = cadence.stats();
+                if stats.partial_decodes > 0 || stats.skipped_silent > 0 {
+                    let _ = command_tx.blocking_send(EngineCommand::ReportDecodeStats(stats));
                 }
-                TranscriberMessage::UpdateModel(model) => {
-                    if runtime.reload_model(model).is_err() {
-                        continue;
+                let _ = command_tx.blocking_send(EngineCommand::TranscriptionFinished);
+            }
+            TranscriberMessage::Cancel => {
+                in_session = false;
+                last_used = Instant::now();
+                cancel.acknowledge();
+                stream.reset();
+                last_emitted.clear();
+                let _ = command_tx.blocking_send(EngineCommand::TranscriptionFinished);
+            }
+            TranscriberMessage::UpdateModel(next, partial) => {
+                if next == config.model && partial == config.partial_model {
+                    continue;
+                }
+                config.model = next;
+                config.partial_model = partial;
+                if config.backend != TranscriptionBackendKind::Local {
+                    continue;
+                }
+                load_generation += 1;
+                pending = None;
+                if backend.is_none() && !loading {
+                    report_health(&command_tx, config.preflight());
+                    continue;
+                }
+                load_in_background(&config, &cancel, &reload_tx, &command_tx, load_generation);
+            }
+            TranscriberMessage::UpdateBackend(next_kind, next_http) => {
+                if next_kind == config.backend && next_http == config.http {
+                    continue;
+                }
+                config.backend = next_kind;
+                config.http = next_http;
+                load_generation += 1;
+                pending = None;
+                if backend.is_none() && !loading {
+                    report_health(&command_tx, config.preflight());
+                    continue;
+                }
+                load_in_background(&config, &cancel, &reload_tx, &command_tx, load_generation);
+            }
+            TranscriberMessage::UpdateLanguage(language) => {
+                config.language = language;
+            }
+            TranscriberMessage::UpdateSensitivity(sensitivity) => {
+                config.sensitivity = sensitivity;
+                speech.set_sensitivity(sensitivity);
+            }
+            TranscriberMessage::UpdateVocabulary(terms, correct) => {
+                config.vocabulary = terms;
+                config.correct_vocabulary = correct;
+                corrector = config.corrector();
+            }
+            TranscriberMessage::UpdateBlocklist(blocklist) => {
+                config.blocklist = blocklist;
+                filter = config.filter();
+            }
+            TranscriberMessage::UpdateDecoding(partial, final_pass) => {
+                config.partial_decoding = partial;
+                config.final_decoding = final_pass;
+            }
+            TranscriberMessage::UpdateThreads(threads) => {
+                config.threads = threads;
+                if let Some(backend) = backend.as_deref_mut() {
+                    backend.set_threads(decode_thread_count(threads));
+                }
+                if let Some((_, next)) = pending.as_mut() {
+                    next.set_threads(decode_thread_count(threads));
+                }
+            }
+            TranscriberMessage::UpdateLoadPolicy(policy, minutes) => {
+                config.load_policy = policy;
+                config.idle_unload_minutes = minutes;
+                if policy == ModelLoadPolicy::Always && backend.is_none() {
+                    match config.open_backend(&cancel) {
+                        Ok(opened) => {
+                            backend = Some(opened);
+                            report_health(&command_tx, Health::Ok);
+                        }
+                        Err(error) => {
+                            eprintln!("transcriber model failed to load: {error}");
+                            report_health(&command_tx, Health::Disabled(error.to_string()));
+                        }
+                    }
+                }
+            }
+            TranscriberMessage::UpdateContextCarry(enabled, seconds) => {
+                config.carry_context = enabled;
+                config.context_window_seconds = seconds;
+                if !enabled {
+                    carried = None;
+                }
+            }
+            TranscriberMessage::UpdateSpokenPunctuation(enabled) => {
+                config.spoken_punctuation = enabled;
+            }
+            TranscriberMessage::UpdateVoiceCommands(enabled) => {
+                config.voice_commands = enabled;
+            }
+            TranscriberMessage::UpdateSnippets(snippets) => {
+                config.snippets = SnippetTable::new(&snippets);
+            }
+            TranscriberMessage::UpdateCommands(commands) => {
+                config.commands = commands;
+            }
+            TranscriberMessage::ClearContext => {
+                carried = None;
+            }
+            TranscriberMessage::IdleCheck => {
+                if !in_session && backend.is_some() && config.unload_due(last_used.elapsed()) {
+                    backend = None;
+                    // A load still in flight would bring the model straight back.
+                    load_generation += 1;
+                    loading = false;
+                }
+            }
+            TranscriberMessage::BackendLoaded(loaded) => {
+                if loaded.generation != load_generation {
+                    continue;
+                }
+                loading = false;
+                match loaded.result {
+                    Ok(next) if in_session && backend.is_some() => {
+                        let model = loaded.label;
+                        let status = ModelLoadStatus::Ready {
+                            model: model.clone(),
+                        };
+                        send_load_status(&command_tx, status);
+                        pending = Some((model, next));
+                    }
+                    Ok(next) => {
+                        // A session that started without a model picks it up here.
+                        if in_session {
+                            language_detection_pending = configure_session(
+                                next.as_ref(),
+                                session_mode,
+                                &config,
+                                &mut options,
+                                &command_tx,
+                            );
+                        }
+                        backend = Some(next);
+                        cadence.reset_speed();
+                        report_health(&command_tx, Health::Ok);
+                        let status = ModelLoadStatus::Active {
+                            model: loaded.label,
+                        };
+                        send_load_status(&command_tx, status);
+                    }
+                    Err(error) if backend.is_none() => {
+                        report_health(&command_tx, Health::Disabled(error.clone()));
+                        let _ = command_tx.blocking_send(EngineCommand::ReportError(format!(
+                            "transcription model failed to load: {error}"
+                        )));
+                        send_load_status(
+                            &command_tx,
+                            ModelLoadStatus::Failed {
+                                model: loaded.label,
+                                error,
+                            },
+                        );
+                    }
+                    Err(error) => {
+                        eprintln!("transcriber model reload failed: {error}");
+                        report_health(
+                            &command_tx,
+                            Health::Degraded(format!("{error}; still using the previous model")),
+                        );
+                        send_load_status(
+                            &command_tx,
+                            ModelLoadStatus::Failed {
+                                model: loaded.label,
+                                error,
+                            },
+                        );
                     }
-                    session_audio.clear();
-                    last_emitted.clear();
                 }
             }
         }
-    });
+
+        // Swapping between sessions keeps a dictation on the model it started
+        // with, so its committed words and the typed text stay consistent.
+        if !in_session {
+            if let Some((model, next)) = pending.take() {
+                backend = Some(next);
+                cadence.reset_speed();
+                report_health(&command_tx, Health::Ok);
+                send_load_status(&command_tx, ModelLoadStatus::Active { model });
+            }
+        }
+    }
+}
+
+/// Sets the session's translation and language for `backend`, falling back
+/// where the backend cannot honour them. Returns whether the language still
+/// has to be detected from the audio.
+fn configure_session(
+    backend: &dyn TranscriptionBackend,
+    mode: DictationMode,
+    config: &TranscriberConfig,
+    options: &mut DecodeOptions,
+    command_tx: &mpsc::Sender<EngineCommand>,
+) -> bool {
+    options.translate = mode == DictationMode::Translate;
+    if options.translate && !backend.multilingual() {
+        let _ = command_tx.blocking_send(EngineCommand::ReportError(
+            "translation needs a multilingual model; typing the English transcript instead"
+                .to_string(),
+        ));
+        options.translate = false;
+    }
+
+    options.language = config.fixed_language().map(str::to_string);
+    if let Some(language) = options.language.as_deref() {
+        if let Err(error) = validate_language(language, backend.multilingual()) {
+            let _ = command_tx.blocking_send(EngineCommand::ReportError(format!(
+                "{error}; dictating in English instead"
+            )));
+            options.language = Some("en".to_string());
+        }
+    }
+    options.language.is_none()
+}
+
+/// Waits for the result of load `generation`, setting aside the messages that
+/// arrive meanwhile. `None` once the worker is shutting down.
+fn wait_for_load(
+    rx: &mut mpsc::Receiver<TranscriberMessage>,
+    backlog: &mut VecDeque<TranscriberMessage>,
+    generation: u64,
+) -> Option<LoadedBackend> {
+    loop {
+        match rx.blocking_recv()? {
+            TranscriberMessage::BackendLoaded(loaded) if loaded.generation == generation => {
+                return Some(loaded);
+            }
+            other => backlog.push_back(other),
+        }
+    }
+}
+
+/// Opens the configured backend on a separate thread so the worker keeps
+/// decoding with the current one; the result comes back as `BackendLoaded`.
+fn load_in_background(
+    config: &TranscriberConfig,
+    cancel: &CancelToken,
+    reload_tx: &mpsc::WeakSender<TranscriberMessage>,
+    command_tx: &mpsc::Sender<EngineCommand>,
+    generation: u64,
+) {
+    let label = config.backend_label();
+    send_load_status(command_tx, ModelLoadStatus::Loading { model: label.clone() });
+
+    let model = label.clone();
+    let config = config.clone();
+    let cancel = cancel.clone();
+    let reload_tx = reload_tx.clone();
+    let spawned = std::thread::Builder::new()
+        .name("lumitype-model-loader".to_string())
+        .spawn(move || {
+            let result = config.open_backend(&cancel).map_err(|error| error.to_string());
+            if let Some(tx) = reload_tx.upgrade() {
+                let loaded = LoadedBackend {
+                    generation,
+                    label,
+                    result,
+                };
+                let _ = tx.blocking_send(TranscriberMessage::BackendLoaded(loaded));
+            }
+        });
+    if let Err(error) = spawned {
+        send_load_status(
+            command_tx,
+            ModelLoadStatus::Failed {
+                model,
+                error: format!("failed to start model loader: {error}"),
+            },
+        );
+    }
+}
+
+fn send_load_status(command_tx: &mpsc::Sender<EngineCommand>, status: ModelLoadStatus) {
+    let _ = command_tx.blocking_send(EngineCommand::ModelLoadChanged(status));
+}
+
+/// Decodes and commits chunks while the window is longer than one decode can
+/// cover, so long sessions reach whisper in pieces that fit its 30 s input.
+/// Chunks are final, so they use the final decoding profile.
+fn commit_full_chunks(
+    backend: &mut dyn TranscriptionBackend,
+    stream: &mut StreamingDecoder,
+    options: &mut DecodeOptions,
+    config: &TranscriberConfig,
+    filter: &HallucinationFilter,
+    cancel: &CancelToken,
+) -> Result<()> {
+    while let Some(end) = stream.chunk_end() {
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+        options.prompt = vocabulary::build_prompt(
+            &config.vocabulary,
+            stream.prompt().as_deref(),
+            &|text| backend.count_prompt_tokens(text),
+        );
+        options.decoding = config.final_decoding;
+        options.final_pass = true;
+        let result = backend.transcribe(&stream.window()[..end], options)?;
+        let words = filter.filter(stream.words(), &result.words);
+        stream.commit_chunk(&words, end);
+    }
+    Ok(())
+}
+
+/// Transcribes a whole 16 kHz mono recording with the local whisper model,
+/// outside any dictation session. Loads its own copy of the model.
+pub fn transcribe_recording(
+    config: &TranscriberConfig,
+    samples_i16: &[i16],
+) -> Result<FinalTranscript> {
+    let mut runtime = TranscriberRuntime::new(
+        &config.model_root,
+        &config.model,
+        decode_thread_count(config.threads),
+        CancelToken::default(),
+    )?;
+    decode_recording(&mut runtime, config, samples_i16)
+}
+
+/// Decodes a recording the way a dictation session would end up decoding
+/// it, in final-quality chunks, but without partials or typing.
+fn decode_recording(
+    backend: &mut dyn TranscriptionBackend,
+    config: &TranscriberConfig,
+    samples_i16: &[i16],
+) -> Result<FinalTranscript> {
+    let cancel = CancelToken::default();
+    let filter = config.filter();
+    let mut options = DecodeOptions {
+        language: config.fixed_language().map(str::to_string),
+        ..DecodeOptions::default()
+    };
+    if options.language.is_none() {
+        let opening = &samples_i16[..samples_i16.len().min(LANGUAGE_DETECT_SAMPLES)];
+        options.language = backend.detect_language(opening)?;
+    }
+
+    let mut stream = StreamingDecoder::new();
+    for piece in samples_i16.chunks(16_000) {
+        stream.push_audio(piece);
+        commit_full_chunks(backend, &mut stream, &mut options, config, &filter, &cancel)?;
+    }
+    options.prompt = vocabulary::build_prompt(
+        &config.vocabulary,
+        stream.prompt().as_deref(),
+        &|text| backend.count_prompt_tokens(text),
+    );
+    options.decoding = config.final_decoding;
+    options.final_pass = true;
+    let result = backend.transcribe(stream.window(), &options)?;
+    let text = stream.finish(&filter.filter(stream.words(), &result.words));
+    let text = normalize_transcript(&config.corrector().correct(&text), true);
+    let words = align_words(&text, stream.words());
+    Ok(FinalTranscript {
+        segments: segments_from_words(&words),
+        words,
+        text,
+        language: options.language.or(result.language),
+    })
+}
+
+/// Presses the keys mapped to `spoken`, or reports that nothing matched.
+fn run_command(commands: &CommandSet, spoken: &str, command_tx: &mpsc::Sender<EngineCommand>) {
+    let spoken = spoken.trim();
+    let command = match commands.lookup(spoken) {
+        Some(chords) => EngineCommand::PressKeys(chords.to_vec()),
+        None if spoken.is_empty() => EngineCommand::ReportError("no command heard".to_string()),
+        None => EngineCommand::ReportError(format!("no command matches \"{spoken}\"")),
+    };
+    let _ = command_tx.blocking_send(command);
+}
+
+fn report_health(command_tx: &mpsc::Sender<EngineCommand>, health: Health) {
+    let _ = command_tx.blocking_send(EngineCommand::ReportHealth(Component::Transcriber, health));
+}
+
+/// Locks the session to the language whisper identifies in the opening audio.
+fn detect_session_language(
+    backend: &mut dyn TranscriptionBackend,
+    opening_audio: &[i16],
+    options: &mut DecodeOptions,
+    command_tx: &mpsc::Sender<EngineCommand>,
+) {
+    match backend.detect_language(opening_audio) {
+        Ok(Some(language)) => {
+            options.language = Some(language.clone());
+            let _ = command_tx.blocking_send(EngineCommand::LanguageDetected(language));
+        }
+        Ok(None) => {}
+        Err(error) => eprintln!("language detection failed: {error}"),
+    }
 }
 
 struct TranscriberRuntime {
-    model_root: PathBuf,
-    model: TranscriptionModel,
     context: WhisperContext,
+    /// Decoder state (KV caches, mel buffers) kept between decodes so each
+    /// partial does not reallocate it.
+    state: Option<WhisperState>,
+    threads: usize,
+    cancel: CancelToken,
 }
 
 impl TranscriberRuntime {
-    fn new(model_root: PathBuf, model: TranscriptionModel) -> Result<Self> {
-        let context = Self::load_context(&model_root, model)?;
+    fn new(
+        model_root: &Path,
+        model: &TranscriptionModel,
+        threads: usize,
+        cancel: CancelToken,
+    ) -> Result<Self> {
+        let context = Self::load_context(model_root, model)?;
         Ok(Self {
-            model_root,
-            model,
             context,
+            state: None,
+            threads,
+            cancel,
         })
     }
 
-    fn reload_model(&mut self, model: TranscriptionModel) -> Result<()> {
-        let context = Self::load_context(&self.model_root, model)?;
-        self.model = model;
-        self.context = context;
-        Ok(())
-    }
-
-    fn load_context(model_root: &PathBuf, model: TranscriptionModel) -> Result<WhisperContext> {
+    fn load_context(model_root: &Path, model: &TranscriptionModel) -> Result<WhisperContext> {
+        models::check_model(model_root, model)?;
         let model_path = model_root.join(model.file_name());
-        if !model_path.exists() {
-            anyhow::bail!("missing whisper model at {}", model_path.display());
-        }
 
         let mut params = WhisperContextParameters::default();
         #[cfg(target_os = "macos")]
@@ -137,42 +1203,294 @@ impl TranscriberRuntime {
         WhisperContext::new_with_params(model_path.to_string_lossy().as_ref(), params)
             .with_context(|| format!("failed to load whisper model {}", model_path.display()))
     }
+}
+
+fn cached_state<'a>(
+    context: &WhisperContext,
+    slot: &'a mut Option<WhisperState>,
+) -> Result<&'a mut WhisperState> {
+    if slot.is_none() {
+        *slot = Some(context.create_state().context("failed to create whisper state")?);
+    }
+    Ok(slot.as_mut().expect("state was just created"))
+}
 
-    fn transcribe(&self, samples_i16: &[i16], finalize: bool) -> Result<String> {
+fn to_float_audio(samples_i16: &[i16]) -> Result<Vec<f32>> {
+    let mut samples = vec![0.0f32; samples_i16.len()];
+    convert_integer_to_float_audio(samples_i16, &mut samples)
+        .context("failed to convert audio to f32")?;
+    Ok(samples)
+}
+
+impl TranscriptionBackend for TranscriberRuntime {
+    fn transcribe(
+        &mut self,
+        samples_i16: &[i16],
+        options: &DecodeOptions,
+    ) -> Result<Transcription> {
         if samples_i16.is_empty() {
-            return Ok(String::new());
+            return Ok(Transcription::default());
         }
 
-        let mut samples = vec![0.0f32; samples_i16.len()];
-        convert_integer_to_float_audio(samples_i16, &mut samples)
-            .context("failed to convert audio to f32")?;
+        let samples = to_float_audio(samples_i16)?;
 
-        let mut state = self.context.create_state().context("failed to create whisper state")?;
+        let language = if self.multilingual() {
+            options.language.as_deref().unwrap_or("auto")
+        } else {
+            "en"
+        };
 
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_n_threads(4);
-        params.set_language(Some("en"));
-        params.set_translate(false);
+        let mut params = FullParams::new(sampling_strategy(&options.decoding));
+        params.set_n_threads(self.threads as i32);
+        params.set_temperature(0.0);
+        params.set_temperature_inc(options.decoding.temperature_increment);
+        params.set_logprob_thold(options.decoding.logprob_threshold);
+        params.set_no_speech_thold(options.decoding.no_speech_threshold);
+        params.set_entropy_thold(options.decoding.entropy_threshold);
+        params.set_language(Some(language));
+        params.set_translate(options.translate);
         params.set_no_context(true);
+        params.set_token_timestamps(true);
+        if let Some(prompt) = options.prompt.as_deref() {
+            params.set_initial_prompt(prompt);
+        }
         params.set_single_segment(false);
         params.set_print_special(false);
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
+        let cancel = self.cancel.clone();
+        params.set_abort_callback_safe(move || cancel.is_cancelled());
 
-        state.full(params, &samples).context("whisper inference failed")?;
+        let eot = self.context.token_eot();
+        let state = cached_state(&self.context, &mut self.state)?;
+        if let Err(error) = state.full(params, &samples) {
+            // An aborted decode can leave partial results behind; start clean.
+            self.state = None;
+            if self.cancel.is_cancelled() {
+                anyhow::bail!("whisper inference cancelled");
+            }
+            return Err(error).context("whisper inference failed");
+        }
 
         let mut raw = String::new();
+        let mut words = Vec::new();
         for segment in state.as_iter() {
+            let mut segment_words = Vec::new();
+            let mut logprob_sum = 0.0f32;
+            let mut text_tokens = 0usize;
+            for idx in 0..segment.n_tokens() {
+                let Some(token) = segment.get_token(idx) else {
+                    continue;
+                };
+                if token.token_id() >= eot {
+                    continue;
+                }
+                let data = token.token_data();
+                logprob_sum += data.p.max(f32::MIN_POSITIVE).ln();
+                text_tokens += 1;
+                append_token(
+                    &mut segment_words,
+                    token.to_str_lossy()?.as_ref(),
+                    (data.t0.max(0) * 10) as u64,
+                    (data.t1.max(0) * 10) as u64,
+                    data.p.clamp(0.0, 1.0),
+                );
+            }
+            let mean_logprob = logprob_sum / text_tokens.max(1) as f32;
+            if filter::is_silence(
+                segment.no_speech_probability(),
+                mean_logprob,
+                &options.decoding,
+            ) {
+                continue;
+            }
             raw.push_str(segment.to_str_lossy()?.as_ref());
+            words.extend(segment_words);
         }
 
-        Ok(normalize_transcript(&raw, finalize))
+        Ok(Transcription {
+            text: raw,
+            language: whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(str::to_string),
+            words,
+        })
+    }
+
+    fn detect_language(&mut self, samples_i16: &[i16]) -> Result<Option<String>> {
+        if !self.multilingual() {
+            return Ok(Some("en".to_string()));
+        }
+        if samples_i16.is_empty() {
+            return Ok(None);
+        }
+
+        let samples = to_float_audio(samples_i16)?;
+        let threads = self.threads;
+        let state = cached_state(&self.context, &mut self.state)?;
+        state
+            .pcm_to_mel(&samples, threads)
+            .context("failed to compute mel spectrogram")?;
+        let (lang_id, _) = state
+            .lang_detect(0, threads)
+            .context("whisper language detection failed")?;
+        Ok(whisper_rs::get_lang_str(lang_id).map(str::to_string))
+    }
+
+    fn multilingual(&self) -> bool {
+        self.context.is_multilingual()
+    }
+
+    fn count_prompt_tokens(&self, text: &str) -> usize {
+        self.context
+            .tokenize(text, vocabulary::MAX_PROMPT_TOKENS * 4)
+            .map(|tokens| tokens.len())
+            .unwrap_or_else(|_| vocabulary::estimate_tokens(text))
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
     }
 }
 
+/// Cascade mode: partials decode on a small, fast model so the overlay keeps
+/// up with speech, while final passes run the accurate model.
+struct CascadeBackend {
+    fast: Box<dyn TranscriptionBackend>,
+    accurate: Box<dyn TranscriptionBackend>,
+}
+
+impl TranscriptionBackend for CascadeBackend {
+    fn transcribe(
+        &mut self,
+        samples_i16: &[i16],
+        options: &DecodeOptions,
+    ) -> Result<Transcription> {
+        if options.final_pass {
+            self.accurate.transcribe(samples_i16, options)
+        } else {
+            self.fast.transcribe(samples_i16, options)
+        }
+    }
+
+    fn detect_language(&mut self, samples_i16: &[i16]) -> Result<Option<String>> {
+        self.accurate.detect_language(samples_i16)
+    }
+
+    /// Both passes have to handle the session's language.
+    fn multilingual(&self) -> bool {
+        self.fast.multilingual() && self.accurate.multilingual()
+    }
+
+    fn count_prompt_tokens(&self, text: &str) -> usize {
+        self.accurate.count_prompt_tokens(text)
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.fast.set_threads(threads);
+        self.accurate.set_threads(threads);
+    }
+
+    fn cascaded(&self) -> bool {
+        true
+    }
+}
+
+fn sampling_strategy(profile: &DecodingProfile) -> SamplingStrategy {
+    if profile.beam_size > 1 {
+        SamplingStrategy::BeamSearch {
+            beam_size: profile.beam_size as i32,
+            // Negative patience keeps whisper.cpp's default.
+            patience: -1.0,
+        }
+    } else {
+        SamplingStrategy::Greedy { best_of: 1 }
+    }
+}
+
+/// Merges a whisper token into the word list; a leading space starts a new
+/// word. A word is only as confident as its least likely token.
+fn append_token(
+    words: &mut Vec<TimedWord>,
+    piece: &str,
+    start_ms: u64,
+    end_ms: u64,
+    probability: f32,
+) {
+    let text = piece.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    match words.last_mut() {
+        Some(word) if !piece.starts_with(char::is_whitespace) => {
+            word.text.push_str(text);
+            word.end_ms = end_ms.max(word.end_ms);
+            word.probability = word.probability.min(probability);
+        }
+        _ => words.push(TimedWord {
+            text: text.to_string(),
+            start_ms,
+            end_ms: end_ms.max(start_ms),
+            probability,
+        }),
+    }
+}
+
+/// Carries the typed spelling (capitalization, punctuation, vocabulary
+/// fixes) over to the decoded words when they still line up one-to-one.
+fn align_words(text: &str, words: &[TimedWord]) -> Vec<TimedWord> {
+    let typed = text.split_whitespace().collect::<Vec<_>>();
+    if typed.len() != words.len() {
+        return words.to_vec();
+    }
+    typed
+        .into_iter()
+        .zip(words)
+        .map(|(typed, word)| TimedWord {
+            text: typed.to_string(),
+            ..word.clone()
+        })
+        .collect()
+}
+
+/// Pause after which a new segment starts even without sentence punctuation.
+const SEGMENT_PAUSE_MS: u64 = 800;
+
+fn segments_from_words(words: &[TimedWord]) -> Vec<TranscriptSegment> {
+    let mut segments = Vec::new();
+    let mut current: Vec<&TimedWord> = Vec::new();
+    for (idx, word) in words.iter().enumerate() {
+        current.push(word);
+        let sentence_end = word.text.ends_with(['.', '!', '?']);
+        let pause = words
+            .get(idx + 1)
+            .map(|next| next.start_ms.saturating_sub(word.end_ms) >= SEGMENT_PAUSE_MS)
+            .unwrap_or(true);
+        if sentence_end || pause {
+            segments.push(TranscriptSegment {
+                text: current
+                    .iter()
+                    .map(|word| word.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                start_ms: current[0].start_ms,
+                end_ms: word.end_ms,
+                probability: current.iter().map(|word| word.probability).sum::<f32>()
+                    / current.len() as f32,
+            });
+            current.clear();
+        }
+    }
+    segments
+}
+
 fn normalize_transcript(raw: &str, finalize: bool) -> String {
-    let trimmed = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Line breaks are typed text (spoken "new line"); other whitespace collapses.
+    let trimmed = raw
+        .split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n");
     if trimmed.is_empty() {
         return String::new();
     }
@@ -189,7 +1507,8 @@ fn normalize_transcript(raw: &str, finalize: bool) -> String {
     out.extend(chars);
 
     if finalize {
-        let needs_terminal = !out.ends_with('.') && !out.ends_with('!') && !out.ends_with('?');
+        // Punctuation or a line break the user dictated stays the last thing typed.
+        let needs_terminal = !out.ends_with(['.', '!', '?', ',', ';', ':', '\n']);
         if needs_terminal {
             out.push('.');
         }
@@ -198,32 +1517,95 @@ fn normalize_transcript(raw: &str, finalize: bool) -> String {
     out
 }
 
-fn transcript_delta(previous: &str, next: &str) -> String {
-    if next.is_empty() {
-        return String::new();
-    }
-    if previous.is_empty() {
-        return next.to_string();
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+        thread::JoinHandle,
+        time::{Duration, Instant},
+    };
+
+    use anyhow::Result;
+    use tokio::sync::mpsc;
+
+    use super::{
+        align_words, append_token, decode_recording, decode_thread_count, normalize_transcript,
+        run_transcriber, sampling_strategy, segments_from_words, validate_language, CancelToken,
+        CascadeBackend, DecodeOptions, DecodingProfile, ModelLoadStatus, SamplingStrategy,
+        StreamingDecoder, TimedWord, TranscriberConfig, TranscriberMessage, TranscriberRuntime,
+        TranscriptEdit, Transcription, TranscriptionBackend, VocabularyCorrector,
+    };
+    use crate::core::{
+        command_mode::{CommandGrammar, CommandSet, CommandSpec, KeyChord},
+        health::{Component, Health},
+        snippets::Snippet,
+        state::{DictationMode, EngineCommand},
+        streaming::synthetic,
+        AudioFrame, EngineSettings, HttpBackendSettings, ModelLoadPolicy, TranscriptionBackendKind,
+    };
+
+    /// Runs `run_transcriber` on its own thread, as `spawn_transcriber_worker`
+    /// does minus the dispatcher. Drop the sender and join to stop it.
+    fn spawn_worker(
+        config: TranscriberConfig,
+        backend: Option<Box<dyn TranscriptionBackend>>,
+    ) -> (
+        mpsc::Sender<TranscriberMessage>,
+        mpsc::Receiver<EngineCommand>,
+        JoinHandle<()>,
+    ) {
+        spawn_cancellable_worker(config, backend, CancelToken::default())
     }
 
-    if let Some(suffix) = next.strip_prefix(previous) {
-        return suffix.to_string();
+    fn spawn_cancellable_worker(
+        config: TranscriberConfig,
+        backend: Option<Box<dyn TranscriptionBackend>>,
+        cancel: CancelToken,
+    ) -> (
+        mpsc::Sender<TranscriberMessage>,
+        mpsc::Receiver<EngineCommand>,
+        JoinHandle<()>,
+    ) {
+        let (tx, rx) = mpsc::channel(64);
+        let (command_tx, command_rx) = mpsc::channel(4_096);
+        let reload_tx = tx.downgrade();
+        let worker = std::thread::spawn(move || {
+            run_transcriber(rx, reload_tx, command_tx, config, backend, cancel)
+        });
+        (tx, command_rx, worker)
     }
 
-    let mut prefix_len = 0usize;
-    for (a, b) in previous.chars().zip(next.chars()) {
-        if a != b {
-            break;
-        }
-        prefix_len += a.len_utf8();
+    /// 16 kHz audio as the capture thread delivers it.
+    fn audio_frame(samples: &[i16]) -> TranscriberMessage {
+        TranscriberMessage::Audio(AudioFrame {
+            samples: samples.to_vec(),
+            sample_rate: 16_000,
+            peak: 0.0,
+        })
     }
 
-    next[prefix_len..].to_string()
-}
+    /// Decodes "forever" until cancelled, like whisper polling its abort callback.
+    struct StuckBackend {
+        cancel: CancelToken,
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::{normalize_transcript, transcript_delta};
+    impl TranscriptionBackend for StuckBackend {
+        fn transcribe(
+            &mut self,
+            _samples: &[i16],
+            _options: &DecodeOptions,
+        ) -> Result<Transcription> {
+            let started = Instant::now();
+            while !self.cancel.is_cancelled() {
+                if started.elapsed() > Duration::from_secs(10) {
+                    anyhow::bail!("decode was never cancelled");
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            anyhow::bail!("whisper inference cancelled")
+        }
+    }
 
     #[test]
     fn normalize_adds_capitalization() {
@@ -236,7 +1618,720 @@ mod tests {
     }
 
     #[test]
-    fn delta_only_emits_suffix() {
-        assert_eq!(transcript_delta("Hello", "Hello world"), " world");
+    fn normalize_keeps_dictated_line_breaks_and_punctuation() {
+        assert_eq!(normalize_transcript("hi  there\n\nBye", true), "Hi there\n\nBye.");
+        assert_eq!(normalize_transcript("notes\n", true), "Notes\n");
+        assert_eq!(normalize_transcript("first,", true), "First,");
+    }
+
+    #[test]
+    fn snippets_expand_after_punctuation_and_capitalization() {
+        let settings = EngineSettings {
+            snippets: vec![Snippet {
+                phrase: "insert my signature".to_string(),
+                text: "Best,\nSam".to_string(),
+            }],
+            ..EngineSettings::default()
+        };
+        let config = TranscriberConfig::from_settings(&settings, PathBuf::new());
+        let corrector = VocabularyCorrector::default();
+        let typed = |raw: &str| config.typed_text(&corrector, raw, true).text;
+        assert_eq!(typed("insert my signature"), "Best,\nSam");
+        assert_eq!(typed("thanks new line insert my signature"), "Thanks\nBest,\nSam");
+        assert_eq!(typed("my signature is short"), "My signature is short.");
+    }
+
+    #[test]
+    fn language_validation_respects_model_capability() {
+        assert!(validate_language("auto", false).is_ok());
+        assert!(validate_language("en", false).is_ok());
+        assert!(validate_language("de", true).is_ok());
+        assert!(validate_language("de", false).is_err());
+        assert!(validate_language("klingon", true).is_err());
+    }
+
+    #[test]
+    fn decoding_profiles_choose_strategy_and_validate() {
+        assert!(matches!(
+            sampling_strategy(&DecodingProfile::fast()),
+            SamplingStrategy::Greedy { best_of: 1 }
+        ));
+        assert!(matches!(
+            sampling_strategy(&DecodingProfile::accurate()),
+            SamplingStrategy::BeamSearch { beam_size: 5, .. }
+        ));
+
+        assert!(DecodingProfile::accurate().validate().is_ok());
+        let too_wide = DecodingProfile {
+            beam_size: 16,
+            ..DecodingProfile::fast()
+        };
+        assert!(too_wide.validate().is_err());
+        let bad_threshold = DecodingProfile {
+            no_speech_threshold: 1.5,
+            ..DecodingProfile::fast()
+        };
+        assert!(bad_threshold.validate().is_err());
+    }
+
+    #[test]
+    fn thread_count_respects_override_and_core_count() {
+        let available = std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(4);
+        assert_eq!(decode_thread_count(1), 1);
+        assert_eq!(decode_thread_count(10_000), available);
+        let automatic = decode_thread_count(0);
+        assert!((1..=8).contains(&automatic));
+        assert!(automatic <= available);
+    }
+
+    #[test]
+    fn cancel_aborts_in_flight_decode() {
+        let cancel = CancelToken::default();
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let backend = Box::new(StuckBackend {
+            cancel: cancel.clone(),
+        });
+        let (tx, mut command_rx, worker) =
+            spawn_cancellable_worker(config, Some(backend), cancel.clone());
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let cancelled_at = Instant::now();
+        cancel.request();
+        tx.blocking_send(TranscriberMessage::Cancel).unwrap();
+
+        let mut finished = 0;
+        while finished < 2 {
+            match command_rx.try_recv() {
+                Ok(EngineCommand::TranscriptionFinished) => finished += 1,
+                Ok(other) => panic!("unexpected command {other:?}"),
+                Err(_) => {
+                    assert!(cancelled_at.elapsed() < Duration::from_secs(2));
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+        }
+        assert!(!cancel.is_cancelled());
+
+        drop(tx);
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn multi_minute_session_is_decoded_in_chunks() {
+        let audio = synthetic::speech(300); // three minutes
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let backend = Box::new(synthetic::SyntheticBackend::default());
+        let (tx, mut command_rx, worker) = spawn_worker(config, Some(backend));
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        for samples in audio.chunks(1_600) {
+            tx.blocking_send(audio_frame(samples)).unwrap();
+        }
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        drop(tx);
+        worker.join().unwrap();
+
+        let mut transcript = None;
+        while let Ok(command) = command_rx.try_recv() {
+            match command {
+                EngineCommand::TranscriptFinalized(finished) => transcript = Some(finished),
+                EngineCommand::ReportError(message) => panic!("unexpected error {message}"),
+                _ => {}
+            }
+        }
+        let transcript = transcript.expect("no final transcript");
+        let expected = (0..300).map(synthetic::word_text).collect::<Vec<_>>();
+        let words = transcript
+            .words
+            .iter()
+            .map(|word| word.text.trim_end_matches('.').to_lowercase())
+            .collect::<Vec<_>>();
+        assert_eq!(words, expected);
+    }
+
+    #[test]
+    fn recordings_decode_in_chunks_with_segments() {
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let mut backend = synthetic::SyntheticBackend::default();
+
+        let transcript =
+            decode_recording(&mut backend, &config, &synthetic::speech(150)).unwrap();
+        assert!(backend.decodes >= 4);
+        assert_eq!(transcript.words.len(), 150);
+        assert!(transcript.text.starts_with("W0 w1 "));
+        assert!(transcript.text.ends_with(" w149."));
+        assert_eq!(transcript.segments.len(), 1);
+        assert_eq!(transcript.segments[0].end_ms, 149 * 600 + 400);
+    }
+
+    /// Stands in for a small model: decodes the synthetic words misspelled.
+    struct SloppyBackend(synthetic::SyntheticBackend);
+
+    impl TranscriptionBackend for SloppyBackend {
+        fn transcribe(
+            &mut self,
+            samples: &[i16],
+            options: &DecodeOptions,
+        ) -> Result<Transcription> {
+            let mut result = self.0.transcribe(samples, options)?;
+            for word in &mut result.words {
+                word.text = word.text.replace('w', "v");
+            }
+            result.text = result.text.replace('w', "v");
+            Ok(result)
+        }
+    }
+
+    #[test]
+    fn cascade_partials_are_replaced_by_the_accurate_final_pass() {
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let backend = Box::new(CascadeBackend {
+            fast: Box::new(SloppyBackend(synthetic::SyntheticBackend::default())),
+            accurate: Box::new(synthetic::SyntheticBackend::default()),
+        });
+        let (tx, mut command_rx, worker) = spawn_worker(config, Some(backend));
+        let audio = synthetic::speech(10);
+        let send_audio = |samples: &[i16]| tx.blocking_send(audio_frame(samples)).unwrap();
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        let half = audio.len() / 2;
+        send_audio(&audio[..half]);
+        // Past the partial cadence, so the next frame is decoded as a partial.
+        std::thread::sleep(Duration::from_millis(400));
+        send_audio(&audio[half..half + 1_600]);
+        send_audio(&audio[half + 1_600..]);
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        drop(tx);
+        worker.join().unwrap();
+
+        let mut typed = String::new();
+        let mut previews = Vec::new();
+        while let Ok(command) = command_rx.try_recv() {
+            if let EngineCommand::TranscriptionEdit(edit) = command {
+                edit.apply(&mut typed);
+                previews.push(typed.clone());
+            }
+        }
+        assert!(previews[0].starts_with("V0 v1 v2 v3 v4"), "{previews:?}");
+        assert_eq!(typed, "W0 w1 w2 w3 w4 w5 w6 w7 w8 w9.");
+    }
+
+    /// Commands up to the next model load status, and that status.
+    fn until_load_status(
+        command_rx: &mut mpsc::Receiver<EngineCommand>,
+    ) -> (Vec<EngineCommand>, ModelLoadStatus) {
+        let mut skipped = Vec::new();
+        loop {
+            match command_rx.blocking_recv() {
+                Some(EngineCommand::ModelLoadChanged(status)) => return (skipped, status),
+                Some(other) => skipped.push(other),
+                None => panic!("worker stopped before reporting a model load"),
+            }
+        }
+    }
+
+    /// Records the prompt of every final-pass decode.
+    struct PromptRecorder {
+        inner: synthetic::SyntheticBackend,
+        prompts: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl TranscriptionBackend for PromptRecorder {
+        fn transcribe(
+            &mut self,
+            samples: &[i16],
+            options: &DecodeOptions,
+        ) -> Result<Transcription> {
+            if options.final_pass {
+                self.prompts.lock().unwrap().push(options.prompt.clone());
+            }
+            self.inner.transcribe(samples, options)
+        }
+    }
+
+    #[test]
+    fn next_session_continues_from_the_previous_one_until_undone() {
+        let settings = EngineSettings {
+            carry_context: true,
+            ..EngineSettings::default()
+        };
+        let config = TranscriberConfig::from_settings(&settings, PathBuf::new());
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let backend = Box::new(PromptRecorder {
+            inner: synthetic::SyntheticBackend::default(),
+            prompts: prompts.clone(),
+        });
+        let (tx, _command_rx, worker) = spawn_worker(config, Some(backend));
+        let dictate = |words: usize| {
+            tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+                .unwrap();
+            tx.blocking_send(audio_frame(&synthetic::speech(words))).unwrap();
+            tx.blocking_send(TranscriberMessage::End).unwrap();
+        };
+
+        dictate(3);
+        dictate(2);
+        tx.blocking_send(TranscriberMessage::ClearContext).unwrap();
+        dictate(2);
+        tx.blocking_send(TranscriberMessage::UpdateContextCarry(false, 60))
+            .unwrap();
+        dictate(2);
+        drop(tx);
+        worker.join().unwrap();
+
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 4);
+        assert!(prompts[1].as_deref().unwrap().starts_with("w0 w1 w2"), "{prompts:?}");
+        // Two words of their own at most; nothing carried over.
+        for prompt in [&prompts[2], &prompts[3]] {
+            assert!(!prompt.as_deref().unwrap_or_default().starts_with("w0 w1 w"));
+        }
+    }
+
+    #[test]
+    fn commands_press_keys_instead_of_typing() {
+        let spec = |phrase: &str, keys: &str| CommandSpec {
+            phrases: vec![phrase.to_string()],
+            keys: vec![keys.to_string()],
+        };
+        let grammar = CommandGrammar {
+            trigger: "w0".to_string(),
+            commands: vec![spec("w0 w1", "Cmd+A"), spec("w1 w2", "Return")],
+        };
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let backend = Box::new(synthetic::SyntheticBackend::default());
+        let (tx, mut command_rx, worker) = spawn_worker(config, Some(backend));
+        tx.blocking_send(TranscriberMessage::UpdateCommands(
+            CommandSet::compile(&grammar).unwrap(),
+        ))
+        .unwrap();
+        let dictate = |mode: DictationMode, words: usize| {
+            tx.blocking_send(TranscriberMessage::Begin(mode)).unwrap();
+            tx.blocking_send(audio_frame(&synthetic::speech(words))).unwrap();
+            tx.blocking_send(TranscriberMessage::End).unwrap();
+        };
+
+        // Hotkey session, spoken trigger, then a hotkey session that matches nothing.
+        dictate(DictationMode::Command, 2);
+        dictate(DictationMode::Transcribe, 3);
+        dictate(DictationMode::Command, 1);
+        drop(tx);
+        worker.join().unwrap();
+
+        let mut pressed = Vec::new();
+        let mut errors = Vec::new();
+        while let Ok(command) = command_rx.try_recv() {
+            match command {
+                EngineCommand::PressKeys(chords) => pressed.push(chords),
+                EngineCommand::ReportError(error) => errors.push(error),
+                EngineCommand::TranscriptFinalized(transcript) => {
+                    panic!("typed {:?}", transcript.text)
+                }
+                _ => {}
+            }
+        }
+        let chord = |keys: &str| vec![KeyChord::parse(keys).unwrap()];
+        assert_eq!(pressed, vec![chord("Cmd+A"), chord("Return")]);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].contains("no command matches"), "{errors:?}");
+    }
+
+    #[test]
+    fn backend_switch_mid_session_waits_for_the_session_to_end() {
+        let config = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        let backend = Box::new(synthetic::SyntheticBackend::default());
+        let (tx, mut command_rx, worker) = spawn_worker(config, Some(backend));
+        let audio = synthetic::speech(10);
+        let send_audio = |samples: &[i16]| tx.blocking_send(audio_frame(samples)).unwrap();
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        send_audio(&audio[..audio.len() / 2]);
+        let http = HttpBackendSettings::default();
+        tx.blocking_send(TranscriberMessage::UpdateBackend(
+            TranscriptionBackendKind::Http,
+            http.clone(),
+        ))
+        .unwrap();
+        let endpoint = http.endpoint.clone();
+        assert_eq!(
+            until_load_status(&mut command_rx).1,
+            ModelLoadStatus::Loading {
+                model: endpoint.clone()
+            }
+        );
+        assert_eq!(
+            until_load_status(&mut command_rx).1,
+            ModelLoadStatus::Ready {
+                model: endpoint.clone()
+            }
+        );
+
+        // The rest of the session still decodes on the synthetic backend.
+        send_audio(&audio[audio.len() / 2..]);
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        let (skipped, status) = until_load_status(&mut command_rx);
+        assert_eq!(status, ModelLoadStatus::Active { model: endpoint });
+        let text = skipped.iter().find_map(|command| match command {
+            EngineCommand::TranscriptFinalized(transcript) => Some(transcript.text.clone()),
+            _ => None,
+        });
+        assert_eq!(text.as_deref(), Some("W0 w1 w2 w3 w4 w5 w6 w7 w8 w9."));
+        assert!(matches!(
+            skipped.last(),
+            Some(EngineCommand::ReportHealth(Component::Transcriber, Health::Ok))
+        ));
+
+        // A failed load leaves the active backend in place.
+        tx.blocking_send(TranscriberMessage::UpdateBackend(
+            TranscriptionBackendKind::Local,
+            http,
+        ))
+        .unwrap();
+        assert!(matches!(
+            until_load_status(&mut command_rx).1,
+            ModelLoadStatus::Loading { .. }
+        ));
+        let (skipped, status) = until_load_status(&mut command_rx);
+        assert!(matches!(status, ModelLoadStatus::Failed { .. }), "{status:?}");
+        assert!(matches!(
+            skipped.as_slice(),
+            [EngineCommand::ReportHealth(
+                Component::Transcriber,
+                Health::Degraded(_)
+            )]
+        ));
+
+        drop(tx);
+        worker.join().unwrap();
+    }
+
+    /// Commands up to and including the end of the current session.
+    fn until_finished(command_rx: &mut mpsc::Receiver<EngineCommand>) -> Vec<EngineCommand> {
+        let mut commands = Vec::new();
+        loop {
+            match command_rx.blocking_recv() {
+                Some(EngineCommand::TranscriptionFinished) => {
+                    commands.push(EngineCommand::TranscriptionFinished);
+                    return commands;
+                }
+                Some(other) => commands.push(other),
+                None => panic!("worker stopped before the session finished"),
+            }
+        }
+    }
+
+    #[test]
+    fn audio_sent_while_a_lazy_model_loads_is_not_lost() {
+        let settings = EngineSettings {
+            model_load_policy: ModelLoadPolicy::OnFirstDictation,
+            ..EngineSettings::default()
+        };
+        let mut config = TranscriberConfig::from_settings(&settings, PathBuf::new());
+        config.opener = |_, _| {
+            std::thread::sleep(Duration::from_millis(500));
+            Ok(Box::new(synthetic::SyntheticBackend::default()))
+        };
+        let (tx, mut command_rx, worker) = spawn_worker(config, None);
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        // The engine forwards audio with `try_send`, so frames are dropped if
+        // the worker stops draining its queue while the model loads.
+        for samples in synthetic::speech(10).chunks(320) {
+            tx.try_send(audio_frame(samples))
+                .expect("audio frame dropped while the model loaded");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        let commands = until_finished(&mut command_rx);
+        drop(tx);
+        worker.join().unwrap();
+
+        let text = commands.iter().find_map(|command| match command {
+            EngineCommand::TranscriptFinalized(transcript) => Some(transcript.text.clone()),
+            _ => None,
+        });
+        assert_eq!(text.as_deref(), Some("W0 w1 w2 w3 w4 w5 w6 w7 w8 w9."));
+        assert!(commands.iter().any(|command| matches!(
+            command,
+            EngineCommand::ModelLoadChanged(ModelLoadStatus::Active { .. })
+        )));
+    }
+
+    #[test]
+    fn lazy_model_is_loaded_at_begin_and_failures_still_finish() {
+        let mut settings = EngineSettings {
+            model_load_policy: ModelLoadPolicy::OnFirstDictation,
+            ..EngineSettings::default()
+        };
+        let root = std::env::temp_dir().join("lumitype-no-models");
+        let config = TranscriberConfig::from_settings(&settings, root.clone());
+        let (tx, mut command_rx, worker) = spawn_worker(config, None);
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe))
+            .unwrap();
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        let commands = until_finished(&mut command_rx);
+        drop(tx);
+        worker.join().unwrap();
+
+        let mut commands = commands
+            .into_iter()
+            .filter(|command| !matches!(command, EngineCommand::ModelLoadChanged(_)));
+        match commands.next() {
+            Some(EngineCommand::ReportHealth(Component::Transcriber, Health::Disabled(reason))) => {
+                assert!(reason.contains("missing whisper model"), "{reason}");
+            }
+            other => panic!("expected the transcriber to be disabled, got {other:?}"),
+        }
+        match commands.next() {
+            Some(EngineCommand::ReportError(message)) => {
+                assert!(message.contains("failed to load"), "{message}");
+            }
+            other => panic!("expected a load error, got {other:?}"),
+        }
+        assert!(matches!(commands.next(), Some(EngineCommand::TranscriptionFinished)));
+
+        settings.model_load_policy = ModelLoadPolicy::UnloadWhenIdle;
+        settings.model_idle_minutes = 5;
+        let config = TranscriberConfig::from_settings(&settings, root);
+        assert!(!config.unload_due(Duration::from_secs(4 * 60)));
+        assert!(config.unload_due(Duration::from_secs(5 * 60)));
+        let always = TranscriberConfig::from_settings(&EngineSettings::default(), PathBuf::new());
+        assert!(!always.unload_due(Duration::from_secs(24 * 60 * 60)));
+    }
+
+    #[test]
+    fn tokens_merge_into_timed_words() {
+        let mut words = Vec::<TimedWord>::new();
+        append_token(&mut words, " Hel", 0, 200, 0.9);
+        append_token(&mut words, "lo", 200, 350, 0.4);
+        append_token(&mut words, ",", 350, 360, 0.8);
+        append_token(&mut words, " world", 500, 900, 0.95);
+        assert_eq!(
+            words,
+            vec![
+                TimedWord {
+                    text: "Hello,".to_string(),
+                    start_ms: 0,
+                    end_ms: 360,
+                    probability: 0.4,
+                },
+                TimedWord {
+                    text: "world".to_string(),
+                    start_ms: 500,
+                    end_ms: 900,
+                    probability: 0.95,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn final_words_take_typed_spelling_and_group_into_segments() {
+        let word = |text: &str, start_ms: u64, end_ms: u64, probability: f32| TimedWord {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            probability,
+        };
+        let decoded = [
+            word("hello", 0, 300, 0.9),
+            word("there.", 350, 700, 0.5),
+            word("next", 800, 1_000, 0.8),
+            word("part", 2_000, 2_400, 0.6),
+        ];
+
+        let words = align_words("Hello there. Next part.", &decoded);
+        assert_eq!(words[0].text, "Hello");
+        assert_eq!(words[3].text, "part.");
+        assert_eq!(words[3].start_ms, 2_000);
+
+        let segments = segments_from_words(&words);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0].text, "Hello there.");
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (0, 700));
+        assert!((segments[0].probability - 0.7).abs() < 1e-6);
+        assert_eq!(segments[1].text, "Next");
+        assert_eq!(segments[2].text, "part.");
+
+        let merged = align_words("LumiType rocks.", &decoded[..3]);
+        assert_eq!(merged, decoded[..3].to_vec());
+    }
+
+    #[test]
+    fn edit_only_emits_suffix() {
+        assert_eq!(
+            TranscriptEdit::between("Hello", "Hello world"),
+            TranscriptEdit {
+                retract: 0,
+                insert: " world".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn edit_retracts_revised_words() {
+        let edit = TranscriptEdit::between("Their cat sat", "There cat sat down");
+        assert_eq!(edit.retract, 10);
+        assert_eq!(edit.insert, "re cat sat down");
+
+        let mut typed = "Their cat sat".to_string();
+        edit.apply(&mut typed);
+        assert_eq!(typed, "There cat sat down");
+    }
+
+    /// Compares streaming and full-session re-decoding on a real recording.
+    /// `LUMI_BENCH_PCM` is raw 16 kHz mono s16le audio; `LUMI_MODEL_DIR` holds
+    /// `ggml-base.en.bin`.
+    #[test]
+    #[ignore]
+    fn streaming_rtf_on_long_recording() {
+        let model_root = std::env::var("LUMI_MODEL_DIR").expect("LUMI_MODEL_DIR");
+        let pcm = std::fs::read(std::env::var("LUMI_BENCH_PCM").expect("LUMI_BENCH_PCM")).unwrap();
+        let audio = pcm
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        let audio_secs = audio.len() as f64 / 16_000.0;
+        let mut runtime = TranscriberRuntime::new(
+            std::path::Path::new(&model_root),
+            &crate::core::TranscriptionModel::base_en(),
+            decode_thread_count(0),
+            CancelToken::default(),
+        )
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let mut stream = StreamingDecoder::new();
+        let mut options = DecodeOptions::default();
+        for chunk in audio.chunks(16 * 350) {
+            stream.push_audio(chunk);
+            options.prompt = stream.prompt();
+            let result = runtime.transcribe(stream.window(), &options).unwrap();
+            stream.apply_hypothesis(&result.words);
+        }
+        options.prompt = stream.prompt();
+        let result = runtime.transcribe(stream.window(), &options).unwrap();
+        let text = stream.finish(&result.words);
+        let streaming_rtf = started.elapsed().as_secs_f64() / audio_secs;
+
+        let started = std::time::Instant::now();
+        let options = DecodeOptions::default();
+        for end in (16 * 350..=audio.len()).step_by(16 * 350) {
+            runtime.transcribe(&audio[..end], &options).unwrap();
+        }
+        let full_rtf = started.elapsed().as_secs_f64() / audio_secs;
+
+        println!("audio {audio_secs:.1}s streaming_rtf={streaming_rtf:.3} full_rtf={full_rtf:.3}");
+        println!("{text}");
+    }
+
+    /// Per-decode latency with a fresh `WhisperState` for every call versus
+    /// the cached one, on the first five seconds of `LUMI_BENCH_PCM`.
+    #[test]
+    #[ignore]
+    fn cached_state_decode_latency() {
+        let model_root = std::env::var("LUMI_MODEL_DIR").expect("LUMI_MODEL_DIR");
+        let pcm = std::fs::read(std::env::var("LUMI_BENCH_PCM").expect("LUMI_BENCH_PCM")).unwrap();
+        let audio = pcm
+            .chunks_exact(2)
+            .take(16_000 * 5)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        let threads = decode_thread_count(0);
+        let mut runtime = TranscriberRuntime::new(
+            std::path::Path::new(&model_root),
+            &crate::core::TranscriptionModel::base_en(),
+            threads,
+            CancelToken::default(),
+        )
+        .unwrap();
+        let options = DecodeOptions::default();
+        const RUNS: u32 = 10;
+
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            runtime.state = None;
+            runtime.transcribe(&audio, &options).unwrap();
+        }
+        let fresh = started.elapsed() / RUNS;
+
+        runtime.transcribe(&audio, &options).unwrap();
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            runtime.transcribe(&audio, &options).unwrap();
+        }
+        let cached = started.elapsed() / RUNS;
+
+        println!("threads={threads} fresh_state={fresh:?} cached_state={cached:?} per decode");
+    }
+
+    /// Resident memory of the worker under the `LUMI_BENCH_POLICY` load
+    /// policy, at startup, after dictating the first five seconds of
+    /// `LUMI_BENCH_PCM`, and after a one-minute idle timeout. Run once per
+    /// policy; freed model memory is not reliably returned within a process.
+    #[test]
+    #[ignore]
+    fn model_load_policy_rss() {
+        let model_root = std::env::var("LUMI_MODEL_DIR").expect("LUMI_MODEL_DIR");
+        let name = std::env::var("LUMI_BENCH_POLICY").expect("LUMI_BENCH_POLICY");
+        let pcm = std::fs::read(std::env::var("LUMI_BENCH_PCM").expect("LUMI_BENCH_PCM")).unwrap();
+        let audio = pcm
+            .chunks_exact(2)
+            .take(16_000 * 5)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        let policy: ModelLoadPolicy = serde_json::from_value(serde_json::json!(name)).unwrap();
+        let settings = EngineSettings {
+            model_load_policy: policy,
+            model_idle_minutes: 1,
+            ..EngineSettings::default()
+        };
+        let config = TranscriberConfig::from_settings(&settings, PathBuf::from(model_root));
+        let rss_mb = || {
+            let pid = std::process::id().to_string();
+            let out = std::process::Command::new("ps")
+                .args(["-o", "rss=", "-p", &pid])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&out.stdout).trim().parse::<f64>().unwrap() / 1024.0
+        };
+
+        // Only `Always` loads at startup, as in `spawn_transcriber_worker`.
+        let backend = (policy == ModelLoadPolicy::Always)
+            .then(|| config.open_backend(&CancelToken::default()).unwrap());
+        let (tx, mut command_rx, worker) = spawn_worker(config, backend);
+        let startup = rss_mb();
+
+        tx.blocking_send(TranscriberMessage::Begin(DictationMode::Transcribe)).unwrap();
+        for chunk in audio.chunks(320) {
+            tx.blocking_send(audio_frame(chunk)).unwrap();
+        }
+        tx.blocking_send(TranscriberMessage::End).unwrap();
+        until_finished(&mut command_rx);
+        let dictated = rss_mb();
+
+        std::thread::sleep(Duration::from_secs(61));
+        tx.blocking_send(TranscriberMessage::IdleCheck).unwrap();
+        // IdleCheck sends nothing back; a cancel behind it marks when it ran.
+        tx.blocking_send(TranscriberMessage::Cancel).unwrap();
+        until_finished(&mut command_rx);
+        let idle = rss_mb();
+
+        println!(
+            "policy={name} startup_rss_mb={startup:.1} dictated_rss_mb={dictated:.1} \
+             idle_rss_mb={idle:.1}"
+        );
+        drop(tx);
+        worker.join().unwrap();
     }
 }
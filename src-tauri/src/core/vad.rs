@@ -19,40 +19,26 @@ pub fn spawn_vad_worker(
     initial_sensitivity: f32,
 ) {
     std::thread::spawn(move || {
-        let mut vad = Vad::new_with_rate_and_mode(SampleRate::Rate16kHz, VadMode::Aggressive);
+        let mut detector = SpeechDetector::new(initial_sensitivity);
         let mut silence_started: Option<Instant> = None;
-        let mut sensitivity = initial_sensitivity.clamp(0.01, 1.0);
         let silence_timeout = Duration::from_secs_f32(1.0);
 
         while let Some(message) = rx.blocking_recv() {
             match message {
                 VadMessage::Begin => {
-                    vad.reset();
+                    detector.reset();
                     silence_started = None;
                 }
                 VadMessage::End => {
                     silence_started = None;
                 }
                 VadMessage::SetSensitivity(next) => {
-                    sensitivity = next.clamp(0.01, 1.0);
+                    detector.set_sensitivity(next);
                 }
                 VadMessage::Audio(frame) => {
                     let resampled = resample_mono_to_16k(&frame.samples, frame.sample_rate);
-                    let energy_threshold = energy_threshold_from_sensitivity(sensitivity);
-                    for chunk in resampled.chunks(320) {
-                        if chunk.len() != 320 {
-                            continue;
-                        }
-
-                        let vad_speech = vad.is_voice_segment(chunk).unwrap_or(false);
-                        let energy_speech = chunk
-                            .iter()
-                            .map(|sample| (*sample as f32).abs() / i16::MAX as f32)
-                            .sum::<f32>()
-                            / chunk.len() as f32
-                            > energy_threshold;
-
-                        if vad_speech || energy_speech {
+                    for chunk in resampled.chunks_exact(FRAME_SAMPLES) {
+                        if detector.is_speech(chunk) {
                             silence_started = None;
                             continue;
                         }
@@ -73,6 +59,54 @@ pub fn spawn_vad_worker(
     });
 }
 
+/// Samples in one 20 ms frame at 16 kHz, the unit the detector classifies.
+const FRAME_SAMPLES: usize = 320;
+
+/// Speech/silence classification of 16 kHz audio: the WebRTC VAD, backed by
+/// an energy threshold scaled by the sensitivity setting.
+pub struct SpeechDetector {
+    vad: Vad,
+    energy_threshold: f32,
+}
+
+impl SpeechDetector {
+    pub fn new(sensitivity: f32) -> Self {
+        Self {
+            vad: Vad::new_with_rate_and_mode(SampleRate::Rate16kHz, VadMode::Aggressive),
+            energy_threshold: energy_threshold_from_sensitivity(sensitivity),
+        }
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.energy_threshold = energy_threshold_from_sensitivity(sensitivity);
+    }
+
+    pub fn reset(&mut self) {
+        self.vad.reset();
+    }
+
+    /// Classifies one 20 ms frame.
+    pub fn is_speech(&mut self, frame: &[i16]) -> bool {
+        let vad_speech = self.vad.is_voice_segment(frame).unwrap_or(false);
+        let energy = frame
+            .iter()
+            .map(|sample| (*sample as f32).abs() / i16::MAX as f32)
+            .sum::<f32>()
+            / frame.len() as f32;
+        vad_speech || energy > self.energy_threshold
+    }
+
+    /// Whether any whole frame of `samples` is speech. A trailing partial
+    /// frame is ignored.
+    pub fn contains_speech(&mut self, samples: &[i16]) -> bool {
+        let mut speech = false;
+        for frame in samples.chunks_exact(FRAME_SAMPLES) {
+            speech |= self.is_speech(frame);
+        }
+        speech
+    }
+}
+
 fn energy_threshold_from_sensitivity(sensitivity: f32) -> f32 {
     // Keep this threshold in a realistic speech-energy range.
     // Higher sensitivity should require less energy to classify as speech.
@@ -109,7 +143,7 @@ pub fn resample_mono_to_16k(samples: &[i16], source_rate: u32) -> Vec<i16> {
 
 #[cfg(test)]
 mod tests {
-    use super::{energy_threshold_from_sensitivity, resample_mono_to_16k};
+    use super::{energy_threshold_from_sensitivity, resample_mono_to_16k, SpeechDetector};
 
     #[test]
     fn resample_keeps_identity_at_16k() {
@@ -130,4 +164,13 @@ mod tests {
         let high = energy_threshold_from_sensitivity(0.9);
         assert!(high < low);
     }
+
+    #[test]
+    fn detector_separates_silence_from_loud_audio() {
+        let mut detector = SpeechDetector::new(0.5);
+        assert!(!detector.contains_speech(&[0; 3_200]));
+        assert!(detector.contains_speech(&[8_000; 3_200]));
+        // A trailing partial frame is not classified.
+        assert!(!detector.contains_speech(&[8_000; 100]));
+    }
 }
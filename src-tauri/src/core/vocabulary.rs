@@ -0,0 +1,252 @@
+/// Whisper keeps at most `n_text_ctx / 2` prompt tokens and silently drops the
+/// oldest ones beyond that.
+pub const MAX_PROMPT_TOKENS: usize = 224;
+/// Share of the prompt reserved for vocabulary; the rest carries recent text.
+const VOCABULARY_TOKENS: usize = MAX_PROMPT_TOKENS / 2;
+const MAX_TERM_CHARS: usize = 64;
+
+/// Conservative token estimate for backends without a tokenizer: about one
+/// token per three letters of each word plus one per punctuation mark.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|word| {
+            let letters = word.chars().filter(|ch| ch.is_alphanumeric()).count();
+            let marks = word.chars().count() - letters;
+            letters.div_ceil(3) + marks
+        })
+        .sum()
+}
+
+/// Trims, collapses whitespace and drops empty or case-insensitive duplicate terms.
+pub fn normalize_terms(terms: &[String]) -> Vec<String> {
+    let mut out = Vec::<String>::new();
+    for term in terms {
+        let term = term
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(MAX_TERM_CHARS)
+            .collect::<String>();
+        if term.is_empty() || out.iter().any(|known| known.eq_ignore_ascii_case(&term)) {
+            continue;
+        }
+        out.push(term);
+    }
+    out
+}
+
+/// Assembles the initial prompt: a glossary of vocabulary terms followed by
+/// as much of the recent `context` as still fits in whisper's prompt budget.
+pub fn build_prompt(
+    vocabulary: &[String],
+    context: Option<&str>,
+    count_tokens: &dyn Fn(&str) -> usize,
+) -> Option<String> {
+    let mut glossary = String::new();
+    for term in vocabulary {
+        let candidate = if glossary.is_empty() {
+            format!("Glossary: {term}.")
+        } else {
+            format!("{}, {term}.", glossary.trim_end_matches('.'))
+        };
+        if count_tokens(&candidate) > VOCABULARY_TOKENS {
+            break;
+        }
+        glossary = candidate;
+    }
+
+    let mut context_words = context
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    loop {
+        let prompt = [glossary.clone(), context_words.join(" ")]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if prompt.is_empty() {
+            return None;
+        }
+        if count_tokens(&prompt) <= MAX_PROMPT_TOKENS {
+            return Some(prompt);
+        }
+        if context_words.is_empty() {
+            return None;
+        }
+        context_words.remove(0);
+    }
+}
+
+/// Rewrites near-miss spellings of vocabulary terms ("lumi type", "Kubernetis")
+/// to their canonical form after decoding.
+#[derive(Debug, Clone, Default)]
+pub struct VocabularyCorrector {
+    terms: Vec<(String, String, usize)>,
+}
+
+impl VocabularyCorrector {
+    pub fn new(vocabulary: &[String]) -> Self {
+        let mut terms = vocabulary
+            .iter()
+            .map(|term| {
+                let words = term.split_whitespace().count();
+                (term.clone(), squash(term), words)
+            })
+            .filter(|(_, key, _)| !key.is_empty())
+            .collect::<Vec<_>>();
+        terms.sort_by_key(|(_, _, words)| std::cmp::Reverse(*words));
+        Self { terms }
+    }
+
+    pub fn correct(&self, text: &str) -> String {
+        if self.terms.is_empty() {
+            return text.to_string();
+        }
+
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let mut out = Vec::<String>::with_capacity(words.len());
+        let mut idx = 0usize;
+        'words: while idx < words.len() {
+            for (canonical, key, term_words) in &self.terms {
+                for span in (1..=term_words + 1).rev() {
+                    let Some(candidate) = words.get(idx..idx + span) else {
+                        continue;
+                    };
+                    let joined = candidate.join(" ");
+                    let leading = joined
+                        .chars()
+                        .take_while(|ch| !ch.is_alphanumeric())
+                        .collect::<String>();
+                    let trailing = joined
+                        .chars()
+                        .rev()
+                        .take_while(|ch| !ch.is_alphanumeric())
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .rev()
+                        .collect::<String>();
+                    if !is_near_miss(&squash(&joined), key) {
+                        continue;
+                    }
+                    out.push(format!("{leading}{canonical}{trailing}"));
+                    idx += span;
+                    continue 'words;
+                }
+            }
+            out.push(words[idx].to_string());
+            idx += 1;
+        }
+        out.join(" ")
+    }
+}
+
+/// Lowercase letters and digits only, so spacing and punctuation do not count.
+pub(crate) fn squash(text: &str) -> String {
+    text.chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_near_miss(candidate: &str, key: &str) -> bool {
+    if candidate.is_empty() {
+        return false;
+    }
+    let allowed = match key.chars().count() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    };
+    let length_gap = candidate.chars().count().abs_diff(key.chars().count());
+    length_gap <= allowed && levenshtein(candidate, key) <= allowed
+}
+
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_prompt, estimate_tokens, normalize_terms, VocabularyCorrector, MAX_PROMPT_TOKENS,
+    };
+
+    fn terms(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn prompt_lists_vocabulary_before_context() {
+        let prompt = build_prompt(
+            &terms(&["LumiType", "Anya Petrova"]),
+            Some("we shipped the build"),
+            &estimate_tokens,
+        )
+        .unwrap();
+        assert_eq!(
+            prompt,
+            "Glossary: LumiType, Anya Petrova. we shipped the build"
+        );
+        assert_eq!(build_prompt(&[], None, &estimate_tokens), None);
+    }
+
+    #[test]
+    fn prompt_stays_within_token_budget() {
+        let vocabulary = (0..200)
+            .map(|idx| format!("Productname{idx}"))
+            .collect::<Vec<_>>();
+        let context = (0..300)
+            .map(|idx| format!("word{idx}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let prompt = build_prompt(&vocabulary, Some(&context), &estimate_tokens).unwrap();
+        assert!(estimate_tokens(&prompt) <= MAX_PROMPT_TOKENS);
+        assert!(prompt.starts_with("Glossary: Productname0, Productname1"));
+        assert!(prompt.ends_with("word299"));
+
+        // A stricter tokenizer still gets a prompt that fits.
+        let strict = |text: &str| text.chars().count();
+        let prompt = build_prompt(&vocabulary, Some(&context), &strict).unwrap();
+        assert!(strict(&prompt) <= MAX_PROMPT_TOKENS);
+    }
+
+    #[test]
+    fn normalizes_and_dedups_terms() {
+        assert_eq!(
+            normalize_terms(&terms(&[
+                "  Kubernetes ",
+                "kubernetes",
+                "",
+                "Anya   Petrova"
+            ])),
+            terms(&["Kubernetes", "Anya Petrova"])
+        );
+    }
+
+    #[test]
+    fn corrects_near_miss_spellings() {
+        let corrector =
+            VocabularyCorrector::new(&terms(&["LumiType", "Kubernetes", "Anya Petrova", "SQL"]));
+        assert_eq!(
+            corrector.correct("I tried lumi type on kubernetis, right?"),
+            "I tried LumiType on Kubernetes, right?"
+        );
+        assert_eq!(corrector.correct("ask Anja Petrova."), "ask Anya Petrova.");
+        assert_eq!(corrector.correct("run the sql query"), "run the SQL query");
+        assert_eq!(corrector.correct("the sal was low"), "the sal was low");
+    }
+}
@@ -0,0 +1,186 @@
+use super::postprocess::normalized;
+
+/// An editing command spoken during dictation that the transcript alone
+/// cannot express, so it is carried out by the injector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceCommand {
+    /// "scratch that" before anything was dictated: delete the last phrase
+    /// of the previous dictation.
+    ScratchThat,
+    /// "delete last word" before anything was dictated.
+    DeleteLastWord,
+    /// "undo that", or "undo" on its own: remove the previous dictation.
+    Undo,
+    /// "cancel that" or "cancel dictation" at the end: drop this dictation.
+    Cancel,
+}
+
+/// A transcript with its editing commands carried out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditedTranscript {
+    pub text: String,
+    /// Commands that reach past this session's own text, in spoken order.
+    pub commands: Vec<VoiceCommand>,
+    /// Any command was spoken; an empty `text` then means the commands
+    /// erased it rather than that nothing was heard.
+    pub applied: bool,
+}
+
+const SCRATCH: &[&str] = &["scratch that"];
+const DELETE_WORD: &[&str] = &["delete last word", "delete the last word"];
+const UNDO: &[&str] = &["undo that", "undo last dictation"];
+const CANCEL: &[&str] = &["cancel that", "cancel dictation"];
+
+/// Applies "scratch that" and "delete last word" to the text spoken before
+/// them and picks out the commands that act on earlier dictations.
+pub fn apply_voice_commands(text: &str) -> EditedTranscript {
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let mut edited = EditedTranscript::default();
+    if words.len() == 1 && normalized(words[0]) == "undo" {
+        edited.commands.push(VoiceCommand::Undo);
+        edited.applied = true;
+        return edited;
+    }
+
+    let mut idx = 0;
+    while idx < words.len() {
+        let rest = &words[idx..];
+        if let Some(len) = phrase_at(rest, CANCEL).filter(|len| *len == rest.len()) {
+            idx += len;
+            edited.text.clear();
+            edited.commands = vec![VoiceCommand::Cancel];
+            edited.applied = true;
+            continue;
+        }
+        if let Some(len) = phrase_at(rest, SCRATCH) {
+            idx += len;
+            edited.applied = true;
+            if edited.text.is_empty() {
+                edited.commands.push(VoiceCommand::ScratchThat);
+            } else {
+                let kept = without_last_phrase(&edited.text).trim_end().len();
+                edited.text.truncate(kept);
+            }
+            continue;
+        }
+        if let Some(len) = phrase_at(rest, DELETE_WORD) {
+            idx += len;
+            edited.applied = true;
+            if edited.text.is_empty() {
+                edited.commands.push(VoiceCommand::DeleteLastWord);
+            } else {
+                let kept = without_last_word(&edited.text).trim_end().len();
+                edited.text.truncate(kept);
+            }
+            continue;
+        }
+        if edited.text.is_empty() {
+            if let Some(len) = phrase_at(rest, UNDO) {
+                idx += len;
+                edited.applied = true;
+                edited.commands.push(VoiceCommand::Undo);
+                continue;
+            }
+        }
+
+        if !edited.text.is_empty() {
+            edited.text.push(' ');
+        }
+        edited.text.push_str(words[idx]);
+        idx += 1;
+    }
+    edited
+}
+
+/// `text` up to the start of its last phrase: everything after the last
+/// sentence end or line break before the final one.
+pub fn without_last_phrase(text: &str) -> &str {
+    let body = text.trim_end().trim_end_matches(['.', '!', '?']);
+    let start = body
+        .rfind(['.', '!', '?', '\n'])
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    &text[..start]
+}
+
+/// `text` up to the start of its last word.
+pub fn without_last_word(text: &str) -> &str {
+    let body = text.trim_end();
+    let start = body
+        .rfind(char::is_whitespace)
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    &text[..start]
+}
+
+/// Length in words of the first of `phrases` that `words` starts with.
+fn phrase_at(words: &[&str], phrases: &[&str]) -> Option<usize> {
+    phrases.iter().find_map(|phrase| {
+        let expected = phrase.split(' ').collect::<Vec<_>>();
+        let matches = words.len() >= expected.len()
+            && expected
+                .iter()
+                .zip(words)
+                .all(|(expected, word)| normalized(word) == *expected);
+        matches.then_some(expected.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_voice_commands, without_last_phrase, without_last_word, VoiceCommand};
+
+    #[test]
+    fn voice_command_table() {
+        use VoiceCommand::*;
+
+        let cases: &[(&str, &str, &[VoiceCommand])] = &[
+            ("I went home scratch that", "", &[]),
+            (
+                "I went home. Scratch that. I went to the office.",
+                "I went to the office.",
+                &[],
+            ),
+            (
+                "First sentence. Second one scratch that",
+                "First sentence.",
+                &[],
+            ),
+            ("See you soon delete last word", "See you", &[]),
+            ("See you soon. Delete the last word.", "See you", &[]),
+            (
+                "one two delete last word delete last word three",
+                "three",
+                &[],
+            ),
+            ("Scratch that.", "", &[ScratchThat]),
+            ("scratch that new text", "new text", &[ScratchThat]),
+            ("delete last word", "", &[DeleteLastWord]),
+            ("Undo.", "", &[Undo]),
+            ("Undo that.", "", &[Undo]),
+            ("undo last dictation", "", &[Undo]),
+            ("undo the changes", "undo the changes", &[]),
+            ("keep it, undo that", "keep it, undo that", &[]),
+            ("Some words. Cancel that.", "", &[Cancel]),
+            ("scratch that cancel dictation", "", &[Cancel]),
+            ("cancel that order please", "cancel that order please", &[]),
+            ("no commands here", "no commands here", &[]),
+            ("", "", &[]),
+        ];
+        for (spoken, text, commands) in cases {
+            let edited = apply_voice_commands(spoken);
+            assert_eq!(edited.text, *text, "{spoken:?}");
+            assert_eq!(edited.commands, *commands, "{spoken:?}");
+            assert_eq!(edited.applied, edited.text != spoken.trim(), "{spoken:?}");
+        }
+    }
+
+    #[test]
+    fn last_phrase_and_word_boundaries() {
+        assert_eq!(without_last_phrase("One. Two three."), "One.");
+        assert_eq!(without_last_phrase("One.\nTwo"), "One.\n");
+        assert_eq!(without_last_phrase("Just one"), "");
+        assert_eq!(without_last_word("Hello there."), "Hello ");
+        assert_eq!(without_last_word("Hello"), "");
+    }
+}
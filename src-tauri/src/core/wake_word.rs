@@ -9,6 +9,7 @@ use libloading::Library;
 use tokio::sync::mpsc;
 
 use super::{
+    health::{Component, Health},
     state::EngineCommand,
     vad::resample_mono_to_16k,
     AudioFrame,
@@ -67,6 +68,8 @@ pub fn spawn_wake_listener(
             Ok(detector) => detector,
             Err(error) => {
                 eprintln!("wake-word disabled: {error}");
+                let reason = format!("{error}; use the push-to-talk hotkey");
+                report_health(&command_tx, Health::Disabled(reason)).await;
                 return;
             }
         };
@@ -76,6 +79,17 @@ pub fn spawn_wake_listener(
                 detector.keyword_path().display(),
                 config.keyword_path.display()
             );
+            let reason = format!(
+                "using fallback keyword {}",
+                detector
+                    .keyword_path()
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            );
+            report_health(&command_tx, Health::Degraded(reason)).await;
+        } else {
+            report_health(&command_tx, Health::Ok).await;
         }
 
         while let Some(frame) = rx.recv().await {
@@ -86,6 +100,12 @@ pub fn spawn_wake_listener(
     });
 }
 
+async fn report_health(command_tx: &mpsc::Sender<EngineCommand>, health: Health) {
+    let _ = command_tx
+        .send(EngineCommand::ReportHealth(Component::WakeWord, health))
+        .await;
+}
+
 fn default_porcupine_library_path() -> PathBuf {
     let arm_path = PathBuf::from("/opt/homebrew/lib/libpv_porcupine.dylib");
     if arm_path.exists() {
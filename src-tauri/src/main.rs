@@ -11,16 +11,24 @@ use std::{
 
 use anyhow::{Context, Result};
 use core::{
+    batch::{self, TranscriptFormat},
+    cadence::DecodeStats,
+    command_mode::{self, CommandSet},
+    health::{Component, ComponentHealth},
+    models::{self, ModelInfo},
     permissions::{self, PermissionStatus},
+    snippets::{self, Snippet},
     state::{EngineCommand, EngineEvent, TrayState},
-    EngineHandle, EngineSettings,
+    transcriber::{self, TranscriberConfig},
+    vocabulary, EngineHandle, EngineSettings, TranscriptionBackendKind,
+    TranscriptionModel,
 };
 use cpal::traits::{DeviceTrait, HostTrait};
 use directories::ProjectDirs;
 use parking_lot::RwLock;
 use tauri::{
     image::Image,
-    menu::{Menu, MenuItem, PredefinedMenuItem},
+    menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager,
 };
@@ -31,6 +39,8 @@ use tauri_plugin_updater::UpdaterExt;
 struct AppState {
     engine: EngineHandle,
     settings_path: PathBuf,
+    commands_path: PathBuf,
+    model_root: PathBuf,
     push_to_talk_hotkey: Arc<RwLock<String>>,
 }
 
@@ -43,20 +53,253 @@ fn get_settings(state: tauri::State<'_, AppState>) -> EngineSettings {
 async fn update_settings(
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
-    next: EngineSettings,
+    mut next: EngineSettings,
 ) -> Result<(), String> {
+    let current = state.engine.settings();
+    let model_info = models::find_model(&state.model_root, &next.model);
+    // Only the local backend opens model files; a whisper server brings its own.
+    let local = next.backend == TranscriptionBackendKind::Local;
+    let model_changed = next.model != current.model || next.backend != current.backend;
+    if local && model_changed && model_info.is_none() {
+        return Err(format!(
+            "model {} is not installed in {}",
+            next.model.id(),
+            state.model_root.display()
+        ));
+    }
+    if let Some(info) = model_info.as_ref().filter(|info| !info.usable()) {
+        if local && model_changed {
+            return Err(format!(
+                "model {} cannot be used: {}",
+                info.id,
+                info.error.as_deref().unwrap_or("unknown problem")
+            ));
+        }
+    }
+    if next.partial_model.as_ref() == Some(&next.model) {
+        next.partial_model = None;
+    }
+    let partial_info = next
+        .partial_model
+        .as_ref()
+        .map(|partial| (partial, models::find_model(&state.model_root, partial)));
+    if let Some((partial, info)) = partial_info.as_ref() {
+        if local && (next.partial_model != current.partial_model || model_changed) {
+            match info {
+                Some(info) if info.usable() => {}
+                Some(info) => {
+                    return Err(format!(
+                        "partials model {} cannot be used: {}",
+                        info.id,
+                        info.error.as_deref().unwrap_or("unknown problem")
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "partials model {} is not installed in {}",
+                        partial.id(),
+                        state.model_root.display()
+                    ))
+                }
+            }
+        }
+    }
+    // In cascade mode both models decode the session's language.
+    let multilingual = next.backend == TranscriptionBackendKind::Http
+        || (model_info.map(|info| info.multilingual).unwrap_or(true)
+            && partial_info
+                .and_then(|(_, info)| info)
+                .map(|info| info.multilingual)
+                .unwrap_or(true));
+    transcriber::validate_language(&next.language, multilingual).map_err(|err| err.to_string())?;
+    next.partial_decoding
+        .validate()
+        .map_err(|err| format!("partial decoding: {err}"))?;
+    next.final_decoding
+        .validate()
+        .map_err(|err| format!("final decoding: {err}"))?;
+
+    next.vocabulary = vocabulary::normalize_terms(&next.vocabulary);
+    next.hallucination_blocklist = vocabulary::normalize_terms(&next.hallucination_blocklist);
+    next.snippets = snippets::normalize_snippets(&next.snippets);
     save_settings(&state.settings_path, &next).map_err(|err| err.to_string())?;
     apply_launch_at_startup(&app, next.launch_at_startup).map_err(|err| err.to_string())?;
     {
         *state.push_to_talk_hotkey.write() = next.push_to_talk_hotkey.clone();
     }
 
-    register_shortcuts(&app, &state.engine, &next.push_to_talk_hotkey)
-        .map_err(|err| err.to_string())?;
+    register_shortcuts(
+        &app,
+        &state.engine,
+        &next.push_to_talk_hotkey,
+        &next.translate_hotkey,
+        &next.command_hotkey,
+    )
+    .map_err(|err| err.to_string())?;
     state.engine.apply_settings(next).await;
     Ok(())
 }
 
+#[tauri::command]
+fn get_vocabulary(state: tauri::State<'_, AppState>) -> Vec<String> {
+    state.engine.settings().vocabulary
+}
+
+#[tauri::command]
+async fn add_vocabulary_term(
+    state: tauri::State<'_, AppState>,
+    term: String,
+) -> Result<Vec<String>, String> {
+    update_vocabulary(&state, |terms| terms.push(term)).await
+}
+
+#[tauri::command]
+async fn remove_vocabulary_term(
+    state: tauri::State<'_, AppState>,
+    term: String,
+) -> Result<Vec<String>, String> {
+    update_vocabulary(&state, |terms| {
+        terms.retain(|known| !known.trim().eq_ignore_ascii_case(term.trim()))
+    })
+    .await
+}
+
+async fn update_vocabulary(
+    state: &AppState,
+    edit: impl FnOnce(&mut Vec<String>),
+) -> Result<Vec<String>, String> {
+    let mut next = state.engine.settings();
+    edit(&mut next.vocabulary);
+    next.vocabulary = vocabulary::normalize_terms(&next.vocabulary);
+
+    save_settings(&state.settings_path, &next).map_err(|err| err.to_string())?;
+    let terms = next.vocabulary.clone();
+    state.engine.apply_settings(next).await;
+    Ok(terms)
+}
+
+/// Adds the snippets in a JSON file to the table; a snippet with a phrase
+/// already in the table replaces it.
+#[tauri::command]
+async fn import_snippets(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<Vec<Snippet>, String> {
+    let imported = snippets::read_snippets(Path::new(&path)).map_err(|err| format!("{err:#}"))?;
+    let mut next = state.engine.settings();
+    next.snippets = snippets::merge_snippets(&next.snippets, &imported);
+
+    save_settings(&state.settings_path, &next).map_err(|err| err.to_string())?;
+    let table = next.snippets.clone();
+    state.engine.apply_settings(next).await;
+    Ok(table)
+}
+
+#[tauri::command]
+fn export_snippets(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let table = state.engine.settings().snippets;
+    snippets::write_snippets(Path::new(&path), &table).map_err(|err| format!("{err:#}"))
+}
+
+#[tauri::command]
+fn get_health(state: tauri::State<'_, AppState>) -> Vec<ComponentHealth> {
+    state.engine.health()
+}
+
+#[tauri::command]
+fn get_diagnostics(state: tauri::State<'_, AppState>) -> DecodeStats {
+    state.engine.decode_stats()
+}
+
+#[tauri::command]
+fn get_commands_path(state: tauri::State<'_, AppState>) -> String {
+    state.commands_path.to_string_lossy().into_owned()
+}
+
+/// Re-reads the command grammar after the user edited it. A file that fails
+/// to parse keeps the previous grammar active.
+#[tauri::command]
+fn reload_commands(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let commands = load_commands(&state.commands_path).map_err(|err| format!("{err:#}"))?;
+    state
+        .engine
+        .send_blocking(EngineCommand::UpdateCommands(commands));
+    Ok(())
+}
+
+#[tauri::command]
+fn list_models(state: tauri::State<'_, AppState>) -> Vec<ModelInfo> {
+    models::scan_models(&state.model_root)
+}
+
+#[tauri::command]
+async fn import_model(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    sha256: Option<String>,
+) -> Result<ModelInfo, String> {
+    let model_root = state.model_root.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        models::import_model(&model_root, Path::new(&path), sha256.as_deref())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn verify_model(state: tauri::State<'_, AppState>, id: String) -> Result<ModelInfo, String> {
+    let model_root = state.model_root.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        models::verify_model(&model_root, &TranscriptionModel::new(id))
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| err.to_string())
+}
+
+/// Transcribes a WAV or raw PCM recording with the selected local model and
+/// writes it next to the input (or to `output`). Returns the written path.
+#[tauri::command]
+async fn transcribe_file(
+    state: tauri::State<'_, AppState>,
+    input: String,
+    output: Option<String>,
+    format: TranscriptFormat,
+) -> Result<String, String> {
+    let input = PathBuf::from(input);
+    let output = output
+        .map(PathBuf::from)
+        .unwrap_or_else(|| input.with_extension(format.extension()));
+    if output == input {
+        return Err("output would overwrite the recording".to_string());
+    }
+    let config =
+        TranscriberConfig::from_settings(&state.engine.settings(), state.model_root.clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        batch::transcribe_file(&config, &input, &output, format)?;
+        Ok::<_, anyhow::Error>(output.to_string_lossy().into_owned())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+    .map_err(|err| format!("{err:#}"))
+}
+
+#[tauri::command]
+fn delete_model(state: tauri::State<'_, AppState>, id: String) -> Result<Vec<ModelInfo>, String> {
+    let model = TranscriptionModel::new(id);
+    let settings = state.engine.settings();
+    let in_use = settings.model == model || settings.partial_model.as_ref() == Some(&model);
+    if settings.backend == TranscriptionBackendKind::Local && in_use {
+        return Err(format!(
+            "model {} is in use; pick another model before deleting it",
+            model.id()
+        ));
+    }
+    models::delete_model(&state.model_root, &model).map_err(|err| err.to_string())?;
+    Ok(models::scan_models(&state.model_root))
+}
+
 #[tauri::command]
 fn list_input_devices() -> Result<Vec<String>, String> {
     let devices = cpal::default_host()
@@ -90,6 +333,7 @@ fn main() {
 fn run() -> Result<()> {
     let settings_path = settings_path()?;
     let settings = load_settings(&settings_path)?;
+    let commands_path = settings_path.with_file_name("commands.json");
 
     tauri::Builder::default()
         .plugin(tauri_plugin_autostart::init(
@@ -103,14 +347,16 @@ fn run() -> Result<()> {
             configure_runtime_env(&app_handle);
 
             let model_root = detect_model_root(&app_handle);
-            let engine = core::spawn_engine(settings.clone(), model_root)
+            let engine = core::spawn_engine(settings.clone(), model_root.clone())
                 .context("failed to start core engine")?;
-            setup_tray(&app_handle, engine.clone())?;
+            let health_items = setup_tray(&app_handle, engine.clone())?;
 
             let hotkey = Arc::new(RwLock::new(settings.push_to_talk_hotkey.clone()));
             let state = AppState {
                 engine: engine.clone(),
                 settings_path: settings_path.clone(),
+                commands_path: commands_path.clone(),
+                model_root,
                 push_to_talk_hotkey: hotkey,
             };
             app.manage(state);
@@ -118,8 +364,18 @@ fn run() -> Result<()> {
                 eprintln!("failed to sync launch at startup: {error}");
             }
 
-            register_shortcuts(&app_handle, &engine, &settings.push_to_talk_hotkey)
-                .context("failed to register keyboard shortcuts")?;
+            register_shortcuts(
+                &app_handle,
+                &engine,
+                &settings.push_to_talk_hotkey,
+                &settings.translate_hotkey,
+                &settings.command_hotkey,
+            )
+            .context("failed to register keyboard shortcuts")?;
+            match load_commands(&commands_path) {
+                Ok(commands) => engine.send_blocking(EngineCommand::UpdateCommands(commands)),
+                Err(error) => eprintln!("failed to load voice commands: {error:#}"),
+            }
             position_overlay_window(&app_handle).ok();
 
             let mut status = permissions::check_permissions();
@@ -128,7 +384,7 @@ fn run() -> Result<()> {
             }
             engine.send_blocking(EngineCommand::PermissionsChecked(status));
 
-            wire_engine_events(app_handle.clone(), engine);
+            wire_engine_events(app_handle.clone(), engine, health_items);
             if !cfg!(debug_assertions) {
                 spawn_update_checker(app_handle);
             }
@@ -137,6 +393,20 @@ fn run() -> Result<()> {
         .invoke_handler(tauri::generate_handler![
             get_settings,
             update_settings,
+            get_health,
+            get_diagnostics,
+            get_commands_path,
+            reload_commands,
+            list_models,
+            import_model,
+            verify_model,
+            delete_model,
+            transcribe_file,
+            get_vocabulary,
+            add_vocabulary_term,
+            remove_vocabulary_term,
+            import_snippets,
+            export_snippets,
             list_input_devices,
             request_permissions,
             open_settings_window
@@ -145,7 +415,16 @@ fn run() -> Result<()> {
         .context("tauri app exited with error")
 }
 
-fn setup_tray(app: &tauri::AppHandle, engine: EngineHandle) -> Result<()> {
+/// Read-only tray lines showing each component's health.
+type HealthItems = Vec<(Component, MenuItem<tauri::Wry>)>;
+
+fn setup_tray(app: &tauri::AppHandle, engine: EngineHandle) -> Result<HealthItems> {
+    let mut health_items = Vec::new();
+    for component in Component::ALL {
+        let label = format!("{}: starting", component.label());
+        health_items.push((component, MenuItem::new(app, label, false, None::<&str>)?));
+    }
+    let separator_health = PredefinedMenuItem::separator(app)?;
     let start_item =
         MenuItem::with_id(app, "start_dictation", "Start Dictation", true, None::<&str>)?;
     let stop_item =
@@ -154,17 +433,18 @@ fn setup_tray(app: &tauri::AppHandle, engine: EngineHandle) -> Result<()> {
     let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
     let separator = PredefinedMenuItem::separator(app)?;
     let separator_bottom = PredefinedMenuItem::separator(app)?;
-    let menu = Menu::with_items(
-        app,
-        &[
-            &start_item,
-            &stop_item,
-            &separator,
-            &settings_item,
-            &separator_bottom,
-            &quit_item,
-        ],
-    )?;
+    let mut items: Vec<&dyn IsMenuItem<tauri::Wry>> =
+        health_items.iter().map(|(_, item)| item as &dyn IsMenuItem<tauri::Wry>).collect();
+    items.extend([
+        &separator_health as &dyn IsMenuItem<tauri::Wry>,
+        &start_item,
+        &stop_item,
+        &separator,
+        &settings_item,
+        &separator_bottom,
+        &quit_item,
+    ]);
+    let menu = Menu::with_items(app, &items)?;
 
     let (rgba, width, height) = tray_icon_rgba(TrayState::Listening);
     let icon = Image::new_owned(rgba, width, height);
@@ -199,7 +479,7 @@ fn setup_tray(app: &tauri::AppHandle, engine: EngineHandle) -> Result<()> {
         })
         .build(app)?;
 
-    Ok(())
+    Ok(health_items)
 }
 
 fn show_settings_window(app: &tauri::AppHandle) -> Result<()> {
@@ -237,6 +517,8 @@ fn register_shortcuts(
     app: &tauri::AppHandle,
     engine: &EngineHandle,
     ptt_hotkey: &str,
+    translate_hotkey: &str,
+    command_hotkey: &str,
 ) -> Result<()> {
     let shortcuts = app.global_shortcut();
     shortcuts.unregister_all()?;
@@ -249,6 +531,26 @@ fn register_shortcuts(
         }
     })?;
 
+    if !translate_hotkey.trim().is_empty() {
+        let translate = normalize_shortcut(translate_hotkey);
+        let translate_engine = engine.clone();
+        shortcuts.on_shortcut(translate.as_str(), move |_app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                translate_engine.send_blocking(EngineCommand::TranslateTriggered);
+            }
+        })?;
+    }
+
+    if !command_hotkey.trim().is_empty() {
+        let command = normalize_shortcut(command_hotkey);
+        let command_engine = engine.clone();
+        shortcuts.on_shortcut(command.as_str(), move |_app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                command_engine.send_blocking(EngineCommand::CommandTriggered);
+            }
+        })?;
+    }
+
     let cancel_engine = engine.clone();
     shortcuts.on_shortcut("Escape", move |_app, _shortcut, event| {
         if event.state == ShortcutState::Pressed {
@@ -315,8 +617,13 @@ fn spawn_update_checker(app: tauri::AppHandle) {
     });
 }
 
-fn wire_engine_events(app: tauri::AppHandle, engine: EngineHandle) {
+fn wire_engine_events(app: tauri::AppHandle, engine: EngineHandle, health_items: HealthItems) {
     let mut rx = engine.subscribe();
+    // Components that reported before the subscription would otherwise keep
+    // their "starting" label until their next change.
+    for entry in engine.health() {
+        set_health_item(&health_items, &entry);
+    }
     tauri::async_runtime::spawn(async move {
         while let Ok(event) = rx.recv().await {
             match event {
@@ -347,20 +654,48 @@ fn wire_engine_events(app: tauri::AppHandle, engine: EngineHandle) {
                         let _ = window.emit("overlay-reset", ());
                     }
                 }
-                EngineEvent::OverlayTextDelta(delta) => {
+                EngineEvent::DictationModeChanged(mode) => {
+                    if let Some(window) = app.get_webview_window("overlay") {
+                        let _ = window.emit("overlay-mode", mode);
+                    }
+                    let _ = app.emit("engine-mode", mode);
+                }
+                EngineEvent::OverlayTextEdit(edit) => {
+                    if let Some(window) = app.get_webview_window("overlay") {
+                        let _ = window.emit("overlay-text", edit);
+                    }
+                }
+                EngineEvent::TranscriptFinalized(transcript) => {
                     if let Some(window) = app.get_webview_window("overlay") {
-                        let _ = window.emit("overlay-text", delta);
+                        let _ = window.emit("overlay-final", transcript.words.clone());
                     }
+                    let _ = app.emit("engine-transcript", transcript);
                 }
                 EngineEvent::OverlayWave(level) => {
                     if let Some(window) = app.get_webview_window("overlay") {
                         let _ = window.emit("overlay-wave", level);
                     }
                 }
+                EngineEvent::LanguageDetected(language) => {
+                    if let Some(window) = app.get_webview_window("overlay") {
+                        let _ = window.emit("overlay-language", language.clone());
+                    }
+                    let _ = app.emit("engine-language", language);
+                }
                 EngineEvent::PermissionsRequired(status) => {
                     let _ = app.emit("permissions-required", status);
                     let _ = show_settings_window(&app);
                 }
+                EngineEvent::ModelLoadChanged(status) => {
+                    let _ = app.emit("model-load", status);
+                }
+                EngineEvent::HealthChanged(entry) => {
+                    set_health_item(&health_items, &entry);
+                    let _ = app.emit("engine-health", entry);
+                }
+                EngineEvent::DecodeStatsChanged(stats) => {
+                    let _ = app.emit("engine-diagnostics", stats);
+                }
                 EngineEvent::Error(message) => {
                     let _ = app.emit("engine-error", message);
                 }
@@ -369,6 +704,12 @@ fn wire_engine_events(app: tauri::AppHandle, engine: EngineHandle) {
     });
 }
 
+fn set_health_item(items: &HealthItems, entry: &ComponentHealth) {
+    if let Some((_, item)) = items.iter().find(|(component, _)| *component == entry.component) {
+        let _ = item.set_text(entry.summary());
+    }
+}
+
 fn set_tray_icon(app: &tauri::AppHandle, state: TrayState) -> Result<()> {
     let Some(tray) = app.tray_by_id("lumitype-tray") else {
         return Ok(());
@@ -431,6 +772,11 @@ fn tray_icon_rgba(state: TrayState) -> (Vec<u8>, u32, u32) {
     (rgba, width, height)
 }
 
+fn load_commands(path: &Path) -> Result<CommandSet> {
+    let grammar = command_mode::load_grammar(path)?;
+    CommandSet::compile(&grammar).with_context(|| format!("invalid {}", path.display()))
+}
+
 fn settings_path() -> Result<PathBuf> {
     let dirs = ProjectDirs::from("com", "LumiType", "LumiType")
         .context("unable to resolve config directory")?;